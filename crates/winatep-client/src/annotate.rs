@@ -0,0 +1,432 @@
+//! Annotated screenshots, for debugging image matches.
+use std::path::{Path, PathBuf};
+
+use glam::Vec2;
+use snafu::prelude::*;
+
+use crate::{
+    BoundingRectangle, CreateDirSnafu, Error, ImageBuffer, ImageMatch, MalformedImageSnafu,
+    SaveImageSnafu, Session, TemplateMatch,
+};
+
+/// Width of the shaded border drawn around each annotation, in pixels.
+const BORDER_WIDTH: f32 = 6.0;
+
+/// Scale of the label font, in pixels per font pixel.
+const LABEL_SCALE: u32 = 2;
+
+/// Outline and label colors, cycled through per annotation.
+const PALETTE: [[u8; 3]; 6] = [
+    [255, 0, 128],
+    [0, 200, 255],
+    [255, 200, 0],
+    [0, 255, 100],
+    [180, 80, 255],
+    [255, 100, 0],
+];
+
+/// A rectangle to draw onto a screenshot, with an optional label and similarity.
+#[derive(Clone, Debug, Default)]
+pub struct Annotation {
+    /// Location of the annotation, in pixels relative to the screenshot.
+    pub location: BoundingRectangle,
+    pub label: Option<String>,
+    pub similarity: Option<f32>,
+}
+
+impl Annotation {
+    pub fn new(location: BoundingRectangle) -> Self {
+        Annotation {
+            location,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_label(mut self, label: impl Into<String>) -> Self {
+        self.label = Some(label.into());
+        self
+    }
+
+    pub fn with_similarity(mut self, similarity: f32) -> Self {
+        self.similarity = Some(similarity);
+        self
+    }
+
+    fn text(&self) -> Option<String> {
+        match (&self.label, self.similarity) {
+            (Some(label), Some(similarity)) => Some(format!("{label} {similarity:.3}")),
+            (Some(label), None) => Some(label.clone()),
+            (None, Some(similarity)) => Some(format!("{similarity:.3}")),
+            (None, None) => None,
+        }
+    }
+}
+
+impl From<BoundingRectangle> for Annotation {
+    fn from(location: BoundingRectangle) -> Self {
+        Annotation::new(location)
+    }
+}
+
+impl From<&ImageMatch> for Annotation {
+    fn from(m: &ImageMatch) -> Self {
+        Annotation::new(m.location).with_similarity(m.similarity)
+    }
+}
+
+impl From<&TemplateMatch> for Annotation {
+    fn from(m: &TemplateMatch) -> Self {
+        Annotation::from(&m.image_match).with_label(format!("#{}", m.template_index))
+    }
+}
+
+/// Render the annotations onto a copy of the captured screen.
+pub fn annotate(
+    capture: &ImageBuffer,
+    annotations: impl IntoIterator<Item = impl Into<Annotation>>,
+) -> Result<image::RgbImage, Error> {
+    let mut img = capture.to_rgb_image().context(MalformedImageSnafu {
+        width: capture.width,
+        height: capture.height,
+        len: capture.buffer.len(),
+    })?;
+    let annotations = annotations.into_iter().map(Into::into).collect::<Vec<_>>();
+    shade_borders(&mut img, &annotations);
+    for (i, annotation) in annotations.iter().enumerate() {
+        let color = PALETTE[i % PALETTE.len()];
+        draw_outline(&mut img, annotation.location, color);
+        if let Some(text) = annotation.text() {
+            draw_label(&mut img, annotation.location, &text, color);
+        }
+    }
+    Ok(img)
+}
+
+/// Render the annotations onto a copy of the captured screen and save it as a PNG.
+pub fn save_annotated(
+    capture: &ImageBuffer,
+    annotations: impl IntoIterator<Item = impl Into<Annotation>>,
+    path: impl AsRef<Path>,
+) -> Result<PathBuf, Error> {
+    let img = annotate(capture, annotations)?;
+    save_png(&img, path)
+}
+
+/// Save an image as a PNG, creating any missing parent directories.
+pub(crate) fn save_png(img: &image::RgbImage, path: impl AsRef<Path>) -> Result<PathBuf, Error> {
+    let path = path.as_ref().to_path_buf();
+    log::debug!("saving image to '{}'", path.display());
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).context(CreateDirSnafu { path: parent })?;
+    }
+    img.save_with_format(&path, image::ImageFormat::Png)
+        .context(SaveImageSnafu { path: &path })?;
+    Ok(path)
+}
+
+/// Returns a file name for a debug artifact that is unique per run and safe on
+/// every OS.
+pub(crate) fn debug_file_name(what: &str, extension: &str) -> String {
+    let millis = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+    format!("{millis}-{}.{extension}", safe_file_name(what))
+}
+
+/// Replaces every character that may not be safe in a file name on some OS.
+fn safe_file_name(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect()
+}
+
+impl Session {
+    /// Capture every screen and save each as `<what>-<screen name>.png` in the
+    /// directory, eg. after a failure.
+    ///
+    /// Failures are logged rather than returned, as this is usually called
+    /// while already handling a failure. Returns the paths saved.
+    pub async fn save_screenshots(&mut self, dir: impl AsRef<Path>, what: &str) -> Vec<PathBuf> {
+        let dir = dir.as_ref();
+        let screens = match self.get_screens().await {
+            Ok(screens) => screens,
+            Err(e) => {
+                log::error!("could not list screens for screenshots: {e}");
+                return vec![];
+            }
+        };
+        let mut paths = vec![];
+        for screen in screens {
+            let path = dir.join(format!("{what}-{}.png", safe_file_name(&screen.name)));
+            match self
+                .save_annotated_capture(&screen.name, None::<Annotation>, path)
+                .await
+            {
+                Ok(path) => paths.push(path),
+                Err(e) => log::error!("could not save a screenshot of '{}': {e}", screen.name),
+            }
+        }
+        paths
+    }
+
+    /// Capture the screen and save it as a PNG with the annotations drawn on.
+    pub(crate) async fn save_annotated_capture(
+        &mut self,
+        screen_name: &str,
+        annotations: impl IntoIterator<Item = Annotation>,
+        path: PathBuf,
+    ) -> Result<PathBuf, Error> {
+        let capture = self.capture_screen(screen_name).await?;
+        save_annotated(&capture, annotations, path)
+    }
+}
+
+/// Darken the pixels just outside each annotation, using the signed distance
+/// to the closest annotation.
+fn shade_borders(img: &mut image::RgbImage, annotations: &[Annotation]) {
+    /// Signed distance field of a point from a box centered at the origin
+    fn sd_box(p: Vec2, half_extents: Vec2) -> f32 {
+        let d = p.abs() - half_extents;
+        d.max(Vec2::splat(0.0)).length() + d.max_element().min(0.0)
+    }
+
+    fn distance_to_rect(p: Vec2, rect: BoundingRectangle) -> f32 {
+        let half_extents = (rect.max - rect.min) / 2.0;
+        sd_box(p - rect.center(), half_extents)
+    }
+
+    if annotations.is_empty() {
+        return;
+    }
+    for (x, y, image::Rgb([r, g, b])) in img.enumerate_pixels_mut() {
+        let p = Vec2::new(x as f32, y as f32);
+        let distance = annotations
+            .iter()
+            .map(|a| distance_to_rect(p, a.location))
+            .min_by(|a, b| a.total_cmp(b))
+            .unwrap_or(f32::INFINITY);
+        if distance > 0.0 && distance <= BORDER_WIDTH {
+            let shadow_percent = distance / BORDER_WIDTH;
+            *r = (*r as f32 * shadow_percent).round() as u8;
+            *g = (*g as f32 * shadow_percent).round() as u8;
+            *b = (*b as f32 * shadow_percent).round() as u8;
+        }
+    }
+}
+
+fn fill_rect(img: &mut image::RgbImage, x: i64, y: i64, width: i64, height: i64, color: [u8; 3]) {
+    let x_range = x.max(0)..(x + width).min(img.width() as i64);
+    for py in y.max(0)..(y + height).min(img.height() as i64) {
+        for px in x_range.clone() {
+            img.put_pixel(px as u32, py as u32, image::Rgb(color));
+        }
+    }
+}
+
+fn draw_outline(img: &mut image::RgbImage, rect: BoundingRectangle, color: [u8; 3]) {
+    let x = rect.min.x.round() as i64;
+    let y = rect.min.y.round() as i64;
+    let width = (rect.max.x - rect.min.x).round() as i64;
+    let height = (rect.max.y - rect.min.y).round() as i64;
+    fill_rect(img, x, y, width, 1, color);
+    fill_rect(img, x, y + height - 1, width, 1, color);
+    fill_rect(img, x, y, 1, height, color);
+    fill_rect(img, x + width - 1, y, 1, height, color);
+}
+
+/// Draw the text on a dark background, just above the rectangle if there is
+/// room, otherwise just inside it.
+fn draw_label(img: &mut image::RgbImage, rect: BoundingRectangle, text: &str, color: [u8; 3]) {
+    let glyph_width = (GLYPH_WIDTH + 1) * LABEL_SCALE;
+    let label_width = (text.chars().count() as u32 * glyph_width + LABEL_SCALE) as i64;
+    let label_height = ((GLYPH_HEIGHT + 2) * LABEL_SCALE) as i64;
+    let x = rect.min.x.round() as i64;
+    let mut y = rect.min.y.round() as i64 - label_height;
+    if y < 0 {
+        y = rect.min.y.round() as i64;
+    }
+    fill_rect(img, x, y, label_width, label_height, [0, 0, 0]);
+    for (i, c) in text.chars().enumerate() {
+        let glyph_x = x + (LABEL_SCALE + i as u32 * glyph_width) as i64;
+        let glyph_y = y + LABEL_SCALE as i64;
+        for (row, bits) in glyph(c).iter().enumerate() {
+            for col in 0..GLYPH_WIDTH {
+                if bits & (1 << (GLYPH_WIDTH - 1 - col)) != 0 {
+                    fill_rect(
+                        img,
+                        glyph_x + (col * LABEL_SCALE) as i64,
+                        glyph_y + (row as u32 * LABEL_SCALE) as i64,
+                        LABEL_SCALE as i64,
+                        LABEL_SCALE as i64,
+                        color,
+                    );
+                }
+            }
+        }
+    }
+}
+
+const GLYPH_WIDTH: u32 = 5;
+const GLYPH_HEIGHT: u32 = 7;
+
+/// Returns the rows of a 5x7 bitmap glyph, most significant bit on the left.
+///
+/// Lowercase letters are drawn as uppercase, unknown characters as `?`.
+fn glyph(c: char) -> [u8; GLYPH_HEIGHT as usize] {
+    match c.to_ascii_uppercase() {
+        '0' => [0x0E, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0E],
+        '1' => [0x04, 0x0C, 0x04, 0x04, 0x04, 0x04, 0x0E],
+        '2' => [0x0E, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1F],
+        '3' => [0x1F, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0E],
+        '4' => [0x02, 0x06, 0x0A, 0x12, 0x1F, 0x02, 0x02],
+        '5' => [0x1F, 0x10, 0x1E, 0x01, 0x01, 0x11, 0x0E],
+        '6' => [0x06, 0x08, 0x10, 0x1E, 0x11, 0x11, 0x0E],
+        '7' => [0x1F, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08],
+        '8' => [0x0E, 0x11, 0x11, 0x0E, 0x11, 0x11, 0x0E],
+        '9' => [0x0E, 0x11, 0x11, 0x0F, 0x01, 0x02, 0x0C],
+        'A' => [0x0E, 0x11, 0x11, 0x11, 0x1F, 0x11, 0x11],
+        'B' => [0x1E, 0x11, 0x11, 0x1E, 0x11, 0x11, 0x1E],
+        'C' => [0x0E, 0x11, 0x10, 0x10, 0x10, 0x11, 0x0E],
+        'D' => [0x1C, 0x12, 0x11, 0x11, 0x11, 0x12, 0x1C],
+        'E' => [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x1F],
+        'F' => [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x10],
+        'G' => [0x0E, 0x11, 0x10, 0x17, 0x11, 0x11, 0x0F],
+        'H' => [0x11, 0x11, 0x11, 0x1F, 0x11, 0x11, 0x11],
+        'I' => [0x0E, 0x04, 0x04, 0x04, 0x04, 0x04, 0x0E],
+        'J' => [0x07, 0x02, 0x02, 0x02, 0x02, 0x12, 0x0C],
+        'K' => [0x11, 0x12, 0x14, 0x18, 0x14, 0x12, 0x11],
+        'L' => [0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x1F],
+        'M' => [0x11, 0x1B, 0x15, 0x15, 0x11, 0x11, 0x11],
+        'N' => [0x11, 0x11, 0x19, 0x15, 0x13, 0x11, 0x11],
+        'O' => [0x0E, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E],
+        'P' => [0x1E, 0x11, 0x11, 0x1E, 0x10, 0x10, 0x10],
+        'Q' => [0x0E, 0x11, 0x11, 0x11, 0x15, 0x12, 0x0D],
+        'R' => [0x1E, 0x11, 0x11, 0x1E, 0x14, 0x12, 0x11],
+        'S' => [0x0F, 0x10, 0x10, 0x0E, 0x01, 0x01, 0x1E],
+        'T' => [0x1F, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04],
+        'U' => [0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E],
+        'V' => [0x11, 0x11, 0x11, 0x11, 0x11, 0x0A, 0x04],
+        'W' => [0x11, 0x11, 0x11, 0x15, 0x15, 0x15, 0x0A],
+        'X' => [0x11, 0x11, 0x0A, 0x04, 0x0A, 0x11, 0x11],
+        'Y' => [0x11, 0x11, 0x11, 0x0A, 0x04, 0x04, 0x04],
+        'Z' => [0x1F, 0x01, 0x02, 0x04, 0x08, 0x10, 0x1F],
+        ' ' => [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00],
+        '.' => [0x00, 0x00, 0x00, 0x00, 0x00, 0x0C, 0x0C],
+        ',' => [0x00, 0x00, 0x00, 0x00, 0x0C, 0x04, 0x08],
+        ':' => [0x00, 0x0C, 0x0C, 0x00, 0x0C, 0x0C, 0x00],
+        '-' => [0x00, 0x00, 0x00, 0x1F, 0x00, 0x00, 0x00],
+        '_' => [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x1F],
+        '=' => [0x00, 0x00, 0x1F, 0x00, 0x1F, 0x00, 0x00],
+        '%' => [0x18, 0x19, 0x02, 0x04, 0x08, 0x13, 0x03],
+        '/' => [0x00, 0x01, 0x02, 0x04, 0x08, 0x10, 0x00],
+        '(' => [0x02, 0x04, 0x08, 0x08, 0x08, 0x04, 0x02],
+        ')' => [0x08, 0x04, 0x02, 0x02, 0x02, 0x04, 0x08],
+        '#' => [0x0A, 0x0A, 0x1F, 0x0A, 0x1F, 0x0A, 0x0A],
+        '\'' => [0x04, 0x04, 0x08, 0x00, 0x00, 0x00, 0x00],
+        _ => [0x0E, 0x11, 0x01, 0x02, 0x04, 0x00, 0x04],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{capture, rect, GREY};
+
+    #[test]
+    fn outline_is_drawn_on_the_rectangle_edges() {
+        let img = annotate(&capture(64, 64), [rect((20.0, 20.0), (40.0, 30.0))]).unwrap();
+        let color = image::Rgb(PALETTE[0]);
+        assert_eq!(*img.get_pixel(20, 20), color);
+        assert_eq!(*img.get_pixel(39, 29), color);
+        assert_eq!(*img.get_pixel(30, 20), color);
+        assert_eq!(*img.get_pixel(20, 25), color);
+        // the inside is untouched
+        assert_eq!(*img.get_pixel(30, 25), GREY);
+    }
+
+    #[test]
+    fn border_is_shaded_darker_closer_to_the_rectangle() {
+        let img = annotate(&capture(64, 64), [rect((20.0, 20.0), (40.0, 30.0))]).unwrap();
+        let near = img.get_pixel(30, 18)[0];
+        let far = img.get_pixel(30, 15)[0];
+        assert!(near < far, "{near} should be darker than {far}");
+        assert!(far < GREY[0]);
+        // beyond the border is untouched
+        assert_eq!(*img.get_pixel(30, 5), GREY);
+    }
+
+    #[test]
+    fn no_annotations_leaves_the_capture_unchanged() {
+        let img = annotate(&capture(8, 8), Vec::<Annotation>::new()).unwrap();
+        assert!(img.pixels().all(|p| *p == GREY));
+    }
+
+    #[test]
+    fn label_goes_above_the_rectangle_or_inside_at_the_top() {
+        let label_height = (GLYPH_HEIGHT + 2) * LABEL_SCALE;
+        let above = annotate(
+            &capture(128, 64),
+            [Annotation::new(rect((10.0, 40.0), (100.0, 60.0))).with_label("A")],
+        )
+        .unwrap();
+        assert_eq!(
+            *above.get_pixel(10, 40 - label_height),
+            image::Rgb([0, 0, 0])
+        );
+
+        let inside = annotate(
+            &capture(128, 64),
+            [Annotation::new(rect((10.0, 2.0), (100.0, 60.0))).with_label("A")],
+        )
+        .unwrap();
+        assert_eq!(*inside.get_pixel(11, 3), image::Rgb([0, 0, 0]));
+    }
+
+    #[test]
+    fn annotations_past_the_edges_are_clipped() {
+        let img = annotate(
+            &capture(16, 16),
+            [Annotation::new(rect((-10.0, -10.0), (30.0, 30.0))).with_label("off screen")],
+        )
+        .unwrap();
+        assert_eq!(img.dimensions(), (16, 16));
+    }
+
+    #[test]
+    fn malformed_captures_are_rejected() {
+        let capture = ImageBuffer {
+            width: 4,
+            height: 4,
+            buffer: vec![0; 5],
+        };
+        let result = annotate(&capture, Vec::<Annotation>::new());
+        assert!(matches!(result, Err(Error::MalformedImage { len: 5, .. })));
+    }
+
+    #[test]
+    fn text_combines_label_and_similarity() {
+        let a = Annotation::default();
+        assert_eq!(a.text(), None);
+        assert_eq!(a.clone().with_similarity(0.5).text().unwrap(), "0.500");
+        assert_eq!(a.clone().with_label("x").text().unwrap(), "x");
+        assert_eq!(
+            a.with_label("x").with_similarity(0.25).text().unwrap(),
+            "x 0.250"
+        );
+    }
+
+    #[test]
+    fn unknown_characters_are_drawn_as_question_marks() {
+        assert_eq!(glyph('a'), glyph('A'));
+        assert_eq!(glyph('é'), glyph('?'));
+    }
+
+    #[test]
+    fn debug_file_names_are_safe() {
+        let name = debug_file_name("main screen/not-found", "png");
+        let (millis, rest) = name.split_once('-').unwrap();
+        assert!(millis.chars().all(|c| c.is_ascii_digit()));
+        assert_eq!(rest, "main_screen_not_found.png");
+    }
+}
@@ -0,0 +1,189 @@
+//! Image assets embedded at compile time.
+use snafu::prelude::*;
+
+use crate::{
+    DecodeAssetSnafu, Error, FindImageScales, ImageBuffer, MissingAssetSnafu, NotFoundSnafu, Screen,
+};
+
+pub use winatep_macros::Assets;
+
+/// A collection of image assets embedded in the binary at compile time.
+///
+/// Derive this with `#[derive(Assets)]`, annotating each field with one or more
+/// `#[asset("path")]` attributes. Paths are relative to the `Cargo.toml` of the
+/// crate deriving `Assets`, and missing files are reported at build time.
+///
+/// Each attribute may also be given a `scale` (the scale factor of the screen the
+/// image was captured on) and an `os` (a value of [`std::env::consts::OS`]).
+/// Variants for the current OS take precedence over variants without an `os`.
+///
+/// ```no_run
+/// use winatep_client::*;
+///
+/// #[derive(Assets)]
+/// struct Templates {
+///     #[asset("../../demo/narrative-logo-small.png")]
+///     logo: ImageBuffer,
+///     #[asset("../../demo/narrative-new-project-next.png", scale = 2.0)]
+///     #[asset("../../demo/narrative-new-project-next.png", scale = 2.0, os = "windows")]
+///     next_button: ScaledImages,
+/// }
+///
+/// let templates = Templates::load().unwrap();
+/// ```
+pub trait Assets: Sized {
+    /// Decode every embedded asset.
+    fn load() -> Result<Self, Error>;
+}
+
+/// One variant of an embedded asset, as generated by `#[derive(Assets)]`.
+#[derive(Clone, Copy, Debug)]
+pub struct AssetVariant {
+    pub path: &'static str,
+    pub os: Option<&'static str>,
+    pub scale_factor: Option<f32>,
+    pub bytes: &'static [u8],
+}
+
+impl AssetVariant {
+    fn decode(&self) -> Result<ImageBuffer, Error> {
+        let img =
+            image::load_from_memory(self.bytes).context(DecodeAssetSnafu { path: self.path })?;
+        Ok(img.into())
+    }
+}
+
+/// Returns the variants that apply to the current OS.
+///
+/// For each scale factor, variants restricted to the current OS replace those
+/// that are not restricted to any OS.
+fn variants_for_current_os(variants: &[AssetVariant]) -> Vec<AssetVariant> {
+    let os = std::env::consts::OS;
+    variants
+        .iter()
+        .filter(|variant| match variant.os {
+            Some(variant_os) => variant_os == os,
+            None => !variants
+                .iter()
+                .any(|other| other.os == Some(os) && other.scale_factor == variant.scale_factor),
+        })
+        .copied()
+        .collect()
+}
+
+/// Types that can be decoded from the variants of an `#[asset]` field.
+pub trait FromAssetVariants: Sized {
+    fn from_variants(field: &'static str, variants: &[AssetVariant]) -> Result<Self, Error>;
+}
+
+/// Decodes the first variant that applies to the current OS.
+impl FromAssetVariants for ImageBuffer {
+    fn from_variants(field: &'static str, variants: &[AssetVariant]) -> Result<Self, Error> {
+        let variants = variants_for_current_os(variants);
+        let variant = variants.first().context(MissingAssetSnafu { field })?;
+        variant.decode()
+    }
+}
+
+/// An image captured on a screen with a known scale factor.
+#[derive(Clone, Debug)]
+pub struct ScaledImage {
+    pub scale_factor: f32,
+    pub image: ImageBuffer,
+}
+
+impl ScaledImage {
+    /// Returns the scales to search for this image at, derived from the target screen.
+    pub fn scales(&self) -> FindImageScales {
+        FindImageScales::FromScreen {
+            template_scale_factor: self.scale_factor,
+        }
+    }
+}
+
+/// An image asset with variants for screens of different scale factors.
+///
+/// Variants without a `scale` are assumed to have been captured at a scale
+/// factor of `1.0`.
+#[derive(Clone, Debug)]
+pub struct ScaledImages {
+    pub variants: Vec<ScaledImage>,
+}
+
+impl ScaledImages {
+    /// Returns the variant with the scale factor closest to the screen's, or
+    /// errors if there are no variants.
+    pub fn for_screen(&self, screen: &Screen) -> Result<&ScaledImage, Error> {
+        self.variants
+            .iter()
+            .min_by(|a, b| {
+                let a = (a.scale_factor - screen.scale_factor).abs();
+                let b = (b.scale_factor - screen.scale_factor).abs();
+                a.total_cmp(&b)
+            })
+            .context(NotFoundSnafu {
+                what: format!("an image variant for screen '{}'", screen.name),
+            })
+    }
+}
+
+impl FromAssetVariants for ScaledImages {
+    fn from_variants(field: &'static str, variants: &[AssetVariant]) -> Result<Self, Error> {
+        let variants = variants_for_current_os(variants)
+            .into_iter()
+            .map(|variant| {
+                Ok(ScaledImage {
+                    scale_factor: variant.scale_factor.unwrap_or(1.0),
+                    image: variant.decode()?,
+                })
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        ensure!(!variants.is_empty(), MissingAssetSnafu { field });
+        Ok(ScaledImages { variants })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn screen(scale_factor: f32) -> Screen {
+        Screen {
+            name: "main".to_owned(),
+            x: 0,
+            y: 0,
+            width: 800,
+            height: 600,
+            scale_factor,
+        }
+    }
+
+    fn image(scale_factor: f32) -> ScaledImage {
+        ScaledImage {
+            scale_factor,
+            image: ImageBuffer {
+                width: 1,
+                height: 1,
+                buffer: vec![0, 0, 0],
+            },
+        }
+    }
+
+    #[test]
+    fn picks_the_closest_scale() {
+        let images = ScaledImages {
+            variants: vec![image(1.0), image(2.0)],
+        };
+        assert_eq!(images.for_screen(&screen(1.75)).unwrap().scale_factor, 2.0);
+        assert_eq!(images.for_screen(&screen(1.25)).unwrap().scale_factor, 1.0);
+    }
+
+    #[test]
+    fn no_variants_is_an_error() {
+        let images = ScaledImages { variants: vec![] };
+        assert!(matches!(
+            images.for_screen(&screen(1.0)),
+            Err(Error::NotFound { .. })
+        ));
+    }
+}
@@ -0,0 +1,461 @@
+//! Visual regression assertions against baseline images stored on disk.
+use std::path::PathBuf;
+
+use snafu::prelude::*;
+
+use crate::{
+    annotate, BaselineMismatchSnafu, BaselineMissingSnafu, BaselineSizeSnafu, BoundingRectangle,
+    Error, ImageBuffer, LoadImageSnafu, MalformedImageSnafu, Session,
+};
+
+/// Environment variable that, when set to anything other than `0` or `false`,
+/// overwrites baselines with the current captures instead of comparing them.
+pub(crate) const UPDATE_BASELINES_VAR: &str = "WINATEP_UPDATE_BASELINES";
+
+/// Environment variable that sets the directory baselines are stored in.
+pub(crate) const BASELINE_DIR_VAR: &str = "WINATEP_BASELINE_DIR";
+
+/// How different two images may be while still being considered a match.
+#[derive(Clone, Copy, Debug, Default)]
+pub enum Tolerance {
+    /// Every pixel must be identical.
+    #[default]
+    Exact,
+    /// Each channel of a pixel may differ by up to `channel_delta`, and up to
+    /// `max_differing_fraction` of all pixels, between 0.0 and 1.0, may
+    /// differ by more than that.
+    PerPixel {
+        channel_delta: u8,
+        max_differing_fraction: f32,
+    },
+    /// The mean perceptual color difference over all pixels, between 0.0 and
+    /// 1.0, may be at most `max_mean_difference`.
+    Perceptual { max_mean_difference: f32 },
+}
+
+/// The result of comparing two images of the same size.
+#[derive(Clone, Copy, Debug)]
+pub struct Comparison {
+    /// Number of pixels that differ beyond the tolerance's channel delta.
+    pub differing_pixels: usize,
+    pub total_pixels: usize,
+    /// Mean perceptual color difference over all pixels, between 0.0 and 1.0.
+    pub mean_difference: f32,
+    /// Whether the images match within the tolerance.
+    pub passed: bool,
+}
+
+impl std::fmt::Display for Comparison {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}/{} pixels differ, mean difference {:.4}",
+            self.differing_pixels, self.total_pixels, self.mean_difference
+        )
+    }
+}
+
+/// Perceptual difference between two colors, between 0.0 and 1.0.
+///
+/// This is the "redmean" weighted euclidean distance, which approximates human
+/// perception far better than plain RGB distance for very little cost.
+fn color_difference(a: image::Rgb<u8>, b: image::Rgb<u8>) -> f32 {
+    let [ar, ag, ab] = a.0.map(f32::from);
+    let [br, bg, bb] = b.0.map(f32::from);
+    let r_mean = (ar + br) / 2.0;
+    let (dr, dg, db) = (ar - br, ag - bg, ab - bb);
+    let distance = ((2.0 + r_mean / 256.0) * dr * dr
+        + 4.0 * dg * dg
+        + (2.0 + (255.0 - r_mean) / 256.0) * db * db)
+        .sqrt();
+    (distance / (3.0 * 255.0)).min(1.0)
+}
+
+fn channel_delta(tolerance: Tolerance) -> u8 {
+    match tolerance {
+        Tolerance::PerPixel { channel_delta, .. } => channel_delta,
+        _ => 0,
+    }
+}
+
+fn pixels_differ(a: image::Rgb<u8>, b: image::Rgb<u8>, channel_delta: u8) -> bool {
+    a.0.iter()
+        .zip(b.0.iter())
+        .any(|(a, b)| a.abs_diff(*b) > channel_delta)
+}
+
+/// Compare two images of the same size.
+///
+/// Returns `None` if the images differ in size.
+pub fn compare_images(
+    expected: &image::RgbImage,
+    actual: &image::RgbImage,
+    tolerance: Tolerance,
+) -> Option<Comparison> {
+    if expected.dimensions() != actual.dimensions() {
+        return None;
+    }
+    let delta = channel_delta(tolerance);
+    let total_pixels = expected.pixels().len();
+    let mut differing_pixels = 0;
+    let mut total_difference = 0.0;
+    for (a, b) in expected.pixels().zip(actual.pixels()) {
+        if pixels_differ(*a, *b, delta) {
+            differing_pixels += 1;
+        }
+        total_difference += color_difference(*a, *b);
+    }
+    let mean_difference = if total_pixels == 0 {
+        0.0
+    } else {
+        total_difference / total_pixels as f32
+    };
+    let passed = match tolerance {
+        Tolerance::Exact => differing_pixels == 0,
+        Tolerance::PerPixel {
+            max_differing_fraction,
+            ..
+        } => differing_pixels as f32 <= max_differing_fraction * total_pixels as f32,
+        Tolerance::Perceptual {
+            max_mean_difference,
+        } => mean_difference <= max_mean_difference,
+    };
+    Some(Comparison {
+        differing_pixels,
+        total_pixels,
+        mean_difference,
+        passed,
+    })
+}
+
+/// Render the differences between two images of the same size.
+///
+/// Matching pixels are drawn as a faded grayscale of `actual`, differing pixels
+/// in red.
+pub fn diff_image(
+    expected: &image::RgbImage,
+    actual: &image::RgbImage,
+    tolerance: Tolerance,
+) -> image::RgbImage {
+    let delta = channel_delta(tolerance);
+    image::RgbImage::from_fn(actual.width(), actual.height(), |x, y| {
+        let b = *actual.get_pixel(x, y);
+        match expected.get_pixel_checked(x, y) {
+            Some(a) if !pixels_differ(*a, b, delta) => {
+                let [r, g, b] = b.0.map(u32::from);
+                let luma = ((r * 3 + g * 6 + b) / 10) as u8;
+                let faded = 128 + luma / 2;
+                image::Rgb([faded, faded, faded])
+            }
+            _ => image::Rgb([255, 0, 0]),
+        }
+    })
+}
+
+/// Crop a region, in pixels, out of a captured screen.
+///
+/// The region is clamped to the bounds of the capture.
+pub(crate) fn crop(
+    capture: &ImageBuffer,
+    region: BoundingRectangle,
+) -> Result<image::RgbImage, Error> {
+    let img = capture.to_rgb_image().context(MalformedImageSnafu {
+        width: capture.width,
+        height: capture.height,
+        len: capture.buffer.len(),
+    })?;
+    let min = region.min.max(glam::Vec2::ZERO).round();
+    let max = region
+        .max
+        .min(glam::Vec2::new(img.width() as f32, img.height() as f32))
+        .round();
+    let size = (max - min).max(glam::Vec2::ZERO);
+    Ok(image::imageops::crop_imm(
+        &img,
+        min.x as u32,
+        min.y as u32,
+        size.x as u32,
+        size.y as u32,
+    )
+    .to_image())
+}
+
+/// Returns the `WINATEP_BASELINE_DIR` environment variable if set, otherwise
+/// `baselines` next to the `Cargo.toml` of the package being run, like the
+/// paths of `#[asset]`s.
+///
+/// Outside of cargo the current directory is used instead.
+pub(crate) fn default_baseline_dir() -> PathBuf {
+    if let Some(dir) = std::env::var_os(BASELINE_DIR_VAR) {
+        return dir.into();
+    }
+    match std::env::var_os("CARGO_MANIFEST_DIR") {
+        Some(manifest_dir) => PathBuf::from(manifest_dir).join("baselines"),
+        None => "baselines".into(),
+    }
+}
+
+fn update_baselines_requested() -> bool {
+    std::env::var(UPDATE_BASELINES_VAR)
+        .map(|value| !matches!(value.as_str(), "" | "0" | "false"))
+        .unwrap_or(false)
+}
+
+impl Session {
+    /// Set the directory baselines are stored in.
+    ///
+    /// Defaults to the `WINATEP_BASELINE_DIR` environment variable if set,
+    /// otherwise `baselines` next to the `Cargo.toml` of the package being run.
+    pub fn set_baseline_dir(&mut self, dir: PathBuf) {
+        self.baseline_dir = dir;
+    }
+
+    /// Assert that a region of a screen looks like the stored baseline image
+    /// of the same name.
+    ///
+    /// `region` is in pixels relative to the screen, like the locations of
+    /// image matches.
+    ///
+    /// Whenever the `WINATEP_UPDATE_BASELINES` environment variable is set,
+    /// the region is stored as the baseline and the assertion passes.
+    /// Otherwise a missing baseline fails the assertion, so that it is not
+    /// silently created on CI.
+    ///
+    /// On failure the actual region, and an image highlighting the differences
+    /// if there is a baseline, are written to the debug directory, or to a
+    /// `winatep-baselines` temporary directory if no debug directory is set,
+    /// never next to the baselines.
+    #[tracing::instrument(skip(self, screen_name))]
+    pub async fn assert_region_matches_baseline(
+        &mut self,
+        screen_name: impl AsRef<str>,
+        name: &str,
+        region: BoundingRectangle,
+        tolerance: Tolerance,
+    ) -> Result<(), Error> {
+        let capture = self.capture_screen(screen_name).await?;
+        let actual = crop(&capture, region)?;
+        let baseline_path = self.baseline_dir.join(format!("{name}.png"));
+
+        if update_baselines_requested() {
+            let path = annotate::save_png(&actual, &baseline_path)?;
+            log::info!("stored baseline '{name}' at '{}'", path.display());
+            return Ok(());
+        }
+        let output_dir = self
+            .debug_dir
+            .clone()
+            .unwrap_or_else(|| std::env::temp_dir().join("winatep-baselines"));
+        if !baseline_path.exists() {
+            let actual_path =
+                annotate::save_png(&actual, output_dir.join(format!("{name}.actual.png")))?;
+            return BaselineMissingSnafu {
+                name,
+                path: baseline_path,
+                actual_path,
+            }
+            .fail();
+        }
+
+        let expected = image::open(&baseline_path)
+            .context(LoadImageSnafu {
+                path: &baseline_path,
+            })?
+            .into_rgb8();
+        let comparison = compare_images(&expected, &actual, tolerance);
+        if let Some(comparison) = comparison.filter(|c| c.passed) {
+            log::debug!("region matches baseline '{name}': {comparison}");
+            return Ok(());
+        }
+
+        let actual_path =
+            annotate::save_png(&actual, output_dir.join(format!("{name}.actual.png")))?;
+        log::info!("saved actual region to '{}'", actual_path.display());
+        let Some(comparison) = comparison else {
+            return BaselineSizeSnafu {
+                name,
+                expected: expected.dimensions(),
+                actual: actual.dimensions(),
+            }
+            .fail();
+        };
+        let diff_path = annotate::save_png(
+            &diff_image(&expected, &actual, tolerance),
+            output_dir.join(format!("{name}.diff.png")),
+        )?;
+        BaselineMismatchSnafu {
+            name,
+            comparison,
+            diff_path,
+        }
+        .fail()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_lite::future::block_on;
+
+    use super::*;
+    use crate::{
+        mock::MockDriver,
+        test_util::{capture, grey, rect, GREY},
+    };
+
+    /// A 10x10 grey image with the first `n` pixels brightened by `delta`.
+    fn brightened(n: u32, delta: u8) -> image::RgbImage {
+        let mut img = grey(10, 10);
+        for i in 0..n {
+            img.put_pixel(i % 10, i / 10, image::Rgb([100 + delta; 3]));
+        }
+        img
+    }
+
+    #[test]
+    fn exact_passes_only_identical_images() {
+        let comparison = compare_images(&grey(10, 10), &grey(10, 10), Tolerance::Exact).unwrap();
+        assert!(comparison.passed);
+        assert_eq!(comparison.differing_pixels, 0);
+        assert_eq!(comparison.total_pixels, 100);
+        assert_eq!(comparison.mean_difference, 0.0);
+
+        let comparison =
+            compare_images(&grey(10, 10), &brightened(1, 1), Tolerance::Exact).unwrap();
+        assert!(!comparison.passed);
+        assert_eq!(comparison.differing_pixels, 1);
+    }
+
+    #[test]
+    fn per_pixel_ignores_small_deltas() {
+        let tolerance = Tolerance::PerPixel {
+            channel_delta: 5,
+            max_differing_fraction: 0.0,
+        };
+        let comparison = compare_images(&grey(10, 10), &brightened(100, 5), tolerance).unwrap();
+        assert!(comparison.passed);
+        assert_eq!(comparison.differing_pixels, 0);
+        let comparison = compare_images(&grey(10, 10), &brightened(1, 6), tolerance).unwrap();
+        assert!(!comparison.passed);
+    }
+
+    #[test]
+    fn per_pixel_allows_a_fraction_of_differing_pixels() {
+        let tolerance = Tolerance::PerPixel {
+            channel_delta: 0,
+            max_differing_fraction: 0.1,
+        };
+        let comparison = compare_images(&grey(10, 10), &brightened(10, 50), tolerance).unwrap();
+        assert!(comparison.passed);
+        assert_eq!(comparison.differing_pixels, 10);
+        let comparison = compare_images(&grey(10, 10), &brightened(11, 50), tolerance).unwrap();
+        assert!(!comparison.passed);
+    }
+
+    #[test]
+    fn perceptual_limits_the_mean_difference() {
+        let black = image::RgbImage::new(10, 10);
+        let white = image::RgbImage::from_pixel(10, 10, image::Rgb([255; 3]));
+        let comparison = compare_images(&black, &white, Tolerance::Exact).unwrap();
+        assert!((comparison.mean_difference - 1.0).abs() < 0.01);
+
+        let slightly = brightened(100, 2);
+        let tolerance = Tolerance::Perceptual {
+            max_mean_difference: 0.01,
+        };
+        let comparison = compare_images(&grey(10, 10), &slightly, tolerance).unwrap();
+        assert!(comparison.passed, "{comparison}");
+        assert_eq!(comparison.differing_pixels, 100);
+        let comparison = compare_images(&black, &white, tolerance).unwrap();
+        assert!(!comparison.passed);
+    }
+
+    #[test]
+    fn images_of_different_sizes_are_not_compared() {
+        assert!(compare_images(&grey(10, 10), &grey(10, 11), Tolerance::Exact).is_none());
+        assert!(
+            compare_images(&grey(0, 0), &grey(0, 0), Tolerance::Exact)
+                .unwrap()
+                .passed
+        );
+    }
+
+    #[test]
+    fn diff_marks_differing_pixels_red() {
+        let diff = diff_image(&grey(10, 10), &brightened(3, 50), Tolerance::Exact);
+        let red = image::Rgb([255, 0, 0]);
+        assert_eq!(diff.pixels().filter(|p| **p == red).count(), 3);
+        assert_eq!(*diff.get_pixel(0, 0), red);
+        // matching pixels are faded towards white
+        let faded = diff.get_pixel(5, 5);
+        assert_ne!(*faded, red);
+        assert!(faded[0] > GREY[0] && faded[0] == faded[1] && faded[1] == faded[2]);
+    }
+
+    #[test]
+    fn diff_of_a_larger_actual_marks_the_extra_pixels() {
+        let diff = diff_image(&grey(5, 5), &grey(10, 5), Tolerance::Exact);
+        assert_eq!(diff.dimensions(), (10, 5));
+        assert_eq!(*diff.get_pixel(7, 2), image::Rgb([255, 0, 0]));
+        assert_ne!(*diff.get_pixel(2, 2), image::Rgb([255, 0, 0]));
+    }
+
+    #[test]
+    fn crop_clamps_the_region_to_the_capture() {
+        let cropped = crop(&capture(10, 10), rect((2.0, 3.0), (6.0, 5.0))).unwrap();
+        assert_eq!(cropped.dimensions(), (4, 2));
+        let clamped = crop(&capture(10, 10), rect((8.0, -2.0), (20.0, 4.0))).unwrap();
+        assert_eq!(clamped.dimensions(), (2, 4));
+    }
+
+    /// A session whose only screen is grey, with baselines and debug output in
+    /// a temporary directory.
+    fn session(name: &str) -> (Session, PathBuf) {
+        let dir = std::env::temp_dir().join(format!("winatep-{}-{name}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let mut session = Session::mock(MockDriver::new());
+        session.set_baseline_dir(dir.join("baselines"));
+        session.set_debug_dir(Some(dir.join("debug")));
+        (session, dir)
+    }
+
+    #[test]
+    fn missing_baselines_fail_without_being_stored() {
+        let (mut session, dir) = session("missing");
+        let result = block_on(session.assert_region_matches_baseline(
+            "main",
+            "button",
+            rect((0.0, 0.0), (10.0, 10.0)),
+            Tolerance::Exact,
+        ));
+        let Err(Error::BaselineMissing { actual_path, .. }) = result else {
+            panic!("unexpected {result:?}");
+        };
+        assert!(actual_path.exists());
+        assert!(!dir.join("baselines/button.png").exists());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn stored_baselines_are_compared() {
+        let (mut session, dir) = session("stored");
+        annotate::save_png(&grey(10, 10), dir.join("baselines/button.png")).unwrap();
+        block_on(session.assert_region_matches_baseline(
+            "main",
+            "button",
+            rect((0.0, 0.0), (10.0, 10.0)),
+            Tolerance::Exact,
+        ))
+        .unwrap();
+        let result = block_on(session.assert_region_matches_baseline(
+            "main",
+            "button",
+            rect((0.0, 0.0), (10.0, 12.0)),
+            Tolerance::Exact,
+        ));
+        assert!(
+            matches!(result, Err(Error::BaselineSize { .. })),
+            "{result:?}"
+        );
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
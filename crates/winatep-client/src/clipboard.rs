@@ -0,0 +1,168 @@
+//! Images, HTML, RTF and file lists on the clipboard.
+use std::time::Instant;
+
+use crate::{
+    ClipboardContent, ClipboardFormat, DriverSnafu, Error, ImageBuffer, InputMessage,
+    OutputMessage, Session, TimeoutSnafu, UnexpectedDriverMessageSnafu,
+};
+
+impl Session {
+    /// Returns the formats currently on the clipboard.
+    #[tracing::instrument(skip(self))]
+    pub async fn get_clipboard_formats(&mut self) -> Result<Vec<ClipboardFormat>, Error> {
+        txrx!(
+            self,
+            InputMessage::GetClipboardFormats,
+            OutputMessage::GotClipboardFormats(formats) => formats
+        )
+    }
+
+    /// Returns the clipboard contents in the given format, or `None` if the
+    /// clipboard does not hold that format.
+    #[tracing::instrument(skip(self))]
+    pub async fn get_clipboard(
+        &mut self,
+        format: ClipboardFormat,
+    ) -> Result<Option<ClipboardContent>, Error> {
+        txrx!(
+            self,
+            InputMessage::GetClipboard(format),
+            OutputMessage::GotClipboard(content) => content
+        )
+    }
+
+    /// Replace the clipboard contents, offering the same contents in every
+    /// given format.
+    #[tracing::instrument(skip_all)]
+    pub async fn set_clipboard(&mut self, contents: Vec<ClipboardContent>) -> Result<(), Error> {
+        txrx!(
+            self,
+            InputMessage::SetClipboard(contents),
+            OutputMessage::DidSetClipboard => ()
+        )
+    }
+
+    /// Returns the image on the clipboard, if any, eg. to check that "Copy
+    /// image" copied the right pixels.
+    #[tracing::instrument(skip(self))]
+    pub async fn get_clipboard_image(&mut self) -> Result<Option<ImageBuffer>, Error> {
+        match self.get_clipboard(ClipboardFormat::Image).await? {
+            Some(ClipboardContent::Image(image)) => Ok(Some(image)),
+            _ => Ok(None),
+        }
+    }
+
+    #[tracing::instrument(skip_all)]
+    pub async fn set_clipboard_image(&mut self, image: ImageBuffer) -> Result<(), Error> {
+        self.set_clipboard(vec![ClipboardContent::Image(image)])
+            .await
+    }
+
+    /// Put references to files on the clipboard, to paste them into an app.
+    #[tracing::instrument(skip(self))]
+    pub async fn set_clipboard_files(&mut self, paths: Vec<String>) -> Result<(), Error> {
+        self.set_clipboard(vec![ClipboardContent::Files(paths)])
+            .await
+    }
+
+    /// Returns the clipboard contents in every format it holds.
+    #[tracing::instrument(skip(self))]
+    pub async fn get_clipboard_contents(&mut self) -> Result<Vec<ClipboardContent>, Error> {
+        let mut contents = vec![];
+        for format in self.get_clipboard_formats().await? {
+            if let Some(content) = self.get_clipboard(format).await? {
+                contents.push(content);
+            }
+        }
+        Ok(contents)
+    }
+
+    /// Run `f`, then restore the clipboard to what it held before, in every
+    /// format, so tests do not clobber the clipboard of whoever runs them.
+    ///
+    /// The clipboard is restored even if `f` fails.
+    ///
+    /// ```no_run
+    /// # async fn run(session: &mut winatep_client::Session) -> Result<(), winatep_client::Error> {
+    /// let pasted = session
+    ///     .with_clipboard_preserved(async |s| {
+    ///         s.set_clipboard_text("Narrative Select").await?;
+    ///         s.get_clipboard_text().await
+    ///     })
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[tracing::instrument(skip_all)]
+    pub async fn with_clipboard_preserved<T>(
+        &mut self,
+        f: impl AsyncFnOnce(&mut Session) -> Result<T, Error>,
+    ) -> Result<T, Error> {
+        let saved = self.get_clipboard_contents().await?;
+        let result = f(self).await;
+        let restored = self.set_clipboard(saved).await;
+        match (result, restored) {
+            (Ok(value), Ok(())) => Ok(value),
+            (Err(e), _) | (Ok(_), Err(e)) => Err(e),
+        }
+    }
+
+    /// Returns a number that increases whenever the clipboard contents change.
+    #[tracing::instrument(skip(self))]
+    pub async fn get_clipboard_change_count(&mut self) -> Result<u64, Error> {
+        txrx!(
+            self,
+            InputMessage::GetClipboardChangeCount,
+            OutputMessage::GotClipboardChangeCount(count) => count
+        )
+    }
+
+    /// Wait until the clipboard changes and return its new contents in every
+    /// format, eg. to verify a copy action.
+    ///
+    /// Only changes after this is called are noticed. If the copy may happen
+    /// before then, get the change count first and use
+    /// [`Session::wait_for_clipboard_change_since`].
+    #[tracing::instrument(skip(self))]
+    pub async fn wait_for_clipboard_change(
+        &mut self,
+        deadline: Instant,
+    ) -> Result<Vec<ClipboardContent>, Error> {
+        let count = self.get_clipboard_change_count().await?;
+        self.wait_for_clipboard_change_since(count, deadline).await
+    }
+
+    /// Wait until the clipboard change count differs from `count` and return
+    /// the new contents in every format.
+    ///
+    /// ```no_run
+    /// # async fn run(session: &mut winatep_client::Session) -> Result<(), winatep_client::Error> {
+    /// use std::time::{Duration, Instant};
+    /// use winatep_client::Key;
+    ///
+    /// let count = session.get_clipboard_change_count().await?;
+    /// session.key_down(Key::Meta).await?;
+    /// session.key_click(Key::Unicode('c')).await?;
+    /// session.key_up(Key::Meta).await?;
+    /// let deadline = Instant::now() + Duration::from_secs(5);
+    /// let copied = session.wait_for_clipboard_change_since(count, deadline).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[tracing::instrument(skip(self))]
+    pub async fn wait_for_clipboard_change_since(
+        &mut self,
+        count: u64,
+        deadline: Instant,
+    ) -> Result<Vec<ClipboardContent>, Error> {
+        while self.get_clipboard_change_count().await? == count {
+            if Instant::now() >= deadline {
+                let what = "clipboard to change".to_owned();
+                log::error!("timed out waiting for {what}");
+                return TimeoutSnafu { what }.fail();
+            }
+            self.pause().await;
+        }
+        self.get_clipboard_contents().await
+    }
+}
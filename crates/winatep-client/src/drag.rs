@@ -0,0 +1,198 @@
+//! Dragging and dropping with the mouse.
+use std::time::Duration;
+
+use snafu::prelude::*;
+
+use crate::{
+    Button, Coordinate, Easing, Error, FindImageFilter, FindImageQuality, FindImageScales,
+    ImageBuffer, Key, MotionSpeed, MouseMotion, NotFoundSnafu, Screen, Session, Token, Vec2,
+};
+
+/// Options for [`Session::drag`].
+#[derive(Clone, Debug)]
+pub struct DragOptions {
+    /// The mouse button held during the drag.
+    pub button: Button,
+    /// Keys held down for the whole drag, eg. [`Key::Option`] to copy rather
+    /// than move in Finder.
+    pub modifiers: Vec<Key>,
+    /// Time to hold the button down before moving, so the OS registers a drag
+    /// rather than a click.
+    pub hold_before_move: Duration,
+    /// Points to hover over on the way, in absolute logical coordinates.
+    pub via: Vec<Vec2>,
+    /// Time to hover over the destination before releasing, so drop targets
+    /// have time to react.
+    pub hover_before_release: Duration,
+    /// How the mouse moves from the start, through `via`, to the destination.
+    pub motion: MouseMotion,
+}
+
+impl Default for DragOptions {
+    fn default() -> Self {
+        DragOptions {
+            button: Button::Left,
+            modifiers: vec![],
+            hold_before_move: Duration::from_millis(200),
+            via: vec![],
+            hover_before_release: Duration::from_millis(200),
+            motion: MouseMotion {
+                easing: Easing::MinimumJerk,
+                speed: MotionSpeed::PixelsPerSecond(800.0),
+                ..Default::default()
+            },
+        }
+    }
+}
+
+impl Session {
+    /// Drag from one point to another, in absolute logical coordinates.
+    ///
+    /// The button and any modifier keys the drag pressed are released even if
+    /// the drag fails part way through. Keys held before the drag are left
+    /// held.
+    #[tracing::instrument(skip(self))]
+    pub async fn drag(&mut self, from: Vec2, to: Vec2, options: &DragOptions) -> Result<(), Error> {
+        self.set_mouse_location(from, Coordinate::Abs).await?;
+        let held_before = self.held_inputs().to_vec();
+        let result = self.drag_held(from, to, options).await;
+
+        // release in reverse order, even if something failed along the way
+        let releases = self
+            .held_inputs()
+            .iter()
+            .filter(|press| !held_before.contains(press))
+            .rev()
+            .filter_map(Token::released)
+            .collect::<Vec<_>>();
+        let mut release_result = Ok(());
+        for release in releases {
+            let released = self.input(release).await;
+            release_result = release_result.and(released);
+        }
+        result.and(release_result)
+    }
+
+    async fn drag_held(
+        &mut self,
+        from: Vec2,
+        to: Vec2,
+        options: &DragOptions,
+    ) -> Result<(), Error> {
+        for key in options.modifiers.iter() {
+            self.key_down(*key).await?;
+        }
+        self.mouse(options.button.clone()).press().await?;
+        self.sleep(options.hold_before_move).await;
+        let path = std::iter::once(from)
+            .chain(options.via.iter().copied())
+            .chain(std::iter::once(to));
+        self.mouse_motion(path, Coordinate::Abs, options.motion)
+            .await?;
+        self.sleep(options.hover_before_release).await;
+        Ok(())
+    }
+
+    /// Find two images within a screen and drag from the first to the second.
+    #[tracing::instrument(skip(self, screen, src, dst))]
+    #[allow(clippy::too_many_arguments)]
+    pub async fn drag_image_to_image(
+        &mut self,
+        screen: &Screen,
+        src: ImageBuffer,
+        dst: ImageBuffer,
+        quality: FindImageQuality,
+        filter: FindImageFilter,
+        scales: FindImageScales,
+        options: &DragOptions,
+    ) -> Result<(), Error> {
+        let mut centers = vec![];
+        for (what, image) in [("drag source", src), ("drop target", dst)] {
+            let found = self
+                .find_scaled_image_in_screen(&screen.name, image, quality, filter, scales.clone())
+                .await?
+                .pop()
+                .context(NotFoundSnafu { what })?;
+            centers.push(screen.abs_point_from_pixel(found.location.center()));
+        }
+        self.drag(centers[0], centers[1], options).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_lite::future::block_on;
+
+    use super::*;
+    use crate::{mock::MockDriver, Direction, InputMessage};
+
+    fn options() -> DragOptions {
+        DragOptions {
+            modifiers: vec![Key::Shift, Key::Control],
+            hold_before_move: Duration::ZERO,
+            hover_before_release: Duration::ZERO,
+            motion: MouseMotion::linear(0.0),
+            ..Default::default()
+        }
+    }
+
+    /// The keys and buttons sent to the driver, in order.
+    fn sent_presses(session: &mut Session) -> Vec<Token> {
+        session
+            .mock_driver()
+            .sent
+            .iter()
+            .filter_map(|msg| match msg {
+                InputMessage::DoInput(token @ (Token::Key(..) | Token::Button(..))) => {
+                    Some(token.clone())
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn releases_in_reverse_order() {
+        let mut session = Session::mock(MockDriver::new());
+        block_on(session.drag(Vec2::ZERO, Vec2::new(10.0, 0.0), &options())).unwrap();
+        assert_eq!(
+            sent_presses(&mut session),
+            [
+                Token::Key(Key::Shift, Direction::Press),
+                Token::Key(Key::Control, Direction::Press),
+                Token::Button(Button::Left, Direction::Press),
+                Token::Button(Button::Left, Direction::Release),
+                Token::Key(Key::Control, Direction::Release),
+                Token::Key(Key::Shift, Direction::Release),
+            ]
+        );
+        assert!(session.held_inputs().is_empty());
+    }
+
+    #[test]
+    fn releases_only_what_was_pressed() {
+        let mut driver = MockDriver::new();
+        driver
+            .failing_inputs
+            .push(Token::Key(Key::Control, Direction::Press));
+        let mut session = Session::mock(driver);
+        block_on(session.key_down(Key::Option)).unwrap();
+        let result = block_on(session.drag(Vec2::ZERO, Vec2::new(10.0, 0.0), &options()));
+        assert!(matches!(result, Err(Error::Driver { .. })), "{result:?}");
+        assert_eq!(
+            sent_presses(&mut session),
+            [
+                Token::Key(Key::Option, Direction::Press),
+                Token::Key(Key::Shift, Direction::Press),
+                Token::Key(Key::Control, Direction::Press),
+                // the failed press may have gone through
+                Token::Key(Key::Control, Direction::Release),
+                Token::Key(Key::Shift, Direction::Release),
+            ]
+        );
+        assert_eq!(
+            session.held_inputs(),
+            [Token::Key(Key::Option, Direction::Press)]
+        );
+    }
+}
@@ -0,0 +1,127 @@
+//! Locating user interface elements through the accessibility tree.
+use std::time::Instant;
+
+use snafu::prelude::*;
+
+use crate::{
+    AccessibilityElement, Button, Coordinate, DriverSnafu, ElementQuery, Error, InputMessage,
+    NotFoundSnafu, OutputMessage, Session, TimeoutSnafu, UnexpectedDriverMessageSnafu, Window,
+};
+
+/// A locator for an accessibility element, see [`Session::element`].
+///
+/// Unlike image templates, elements are found by role, label and value, so
+/// they survive theme and font changes. Fall back to image search for apps
+/// that do not expose an accessibility tree.
+pub struct Element<'a> {
+    session: &'a mut Session,
+    query: ElementQuery,
+}
+
+impl Element<'_> {
+    /// Returns every element matching the query.
+    #[tracing::instrument(skip(self), fields(query = ?self.query))]
+    pub async fn find_all(self) -> Result<Vec<AccessibilityElement>, Error> {
+        self.session.find_elements(self.query).await
+    }
+
+    /// Returns the first element matching the query.
+    #[tracing::instrument(skip(self), fields(query = ?self.query))]
+    pub async fn find(mut self) -> Result<AccessibilityElement, Error> {
+        self.first().await
+    }
+
+    /// Wait until an element matches the query and return the first match.
+    #[tracing::instrument(skip(self), fields(query = ?self.query))]
+    pub async fn wait(self, deadline: Instant) -> Result<AccessibilityElement, Error> {
+        loop {
+            let found = self.session.find_elements(self.query.clone()).await?;
+            if let Some(element) = found.into_iter().next() {
+                return Ok(element);
+            }
+            if Instant::now() >= deadline {
+                let what = format!("element {:?}", self.query);
+                log::error!("timed out waiting for {what}");
+                return TimeoutSnafu { what }.fail();
+            }
+            self.session.pause().await;
+        }
+    }
+
+    /// Move the mouse to the center of the first matching element and click it.
+    #[tracing::instrument(skip(self), fields(query = ?self.query))]
+    pub async fn click(mut self) -> Result<AccessibilityElement, Error> {
+        let element = self.first().await?;
+        self.session
+            .set_mouse_location(element.bounds.center(), Coordinate::Abs)
+            .await?;
+        self.session.mouse(Button::Left).click(1).await?;
+        Ok(element)
+    }
+
+    /// Returns the value of the first matching element, eg. the contents of a
+    /// text field, or its label if it has no value.
+    #[tracing::instrument(skip(self), fields(query = ?self.query))]
+    pub async fn read(mut self) -> Result<String, Error> {
+        let element = self.first().await?;
+        Ok(element.value.or(element.name).unwrap_or_default())
+    }
+
+    async fn first(&mut self) -> Result<AccessibilityElement, Error> {
+        self.session
+            .find_elements(self.query.clone())
+            .await?
+            .into_iter()
+            .next()
+            .context(NotFoundSnafu {
+                what: format!("element {:?}", self.query),
+            })
+    }
+}
+
+impl Session {
+    /// Returns a locator for accessibility elements matching the query.
+    ///
+    /// ```no_run
+    /// # async fn run(session: &mut winatep_client::Session) -> Result<(), winatep_client::Error> {
+    /// use winatep_client::ElementQuery;
+    ///
+    /// session.element(ElementQuery::role("button").name("Export")).click().await?;
+    /// let title = session.element(ElementQuery::role("text field")).read().await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn element(&mut self, query: ElementQuery) -> Element<'_> {
+        Element {
+            session: self,
+            query,
+        }
+    }
+
+    /// Returns every accessibility element matching the query, frontmost
+    /// window first.
+    #[tracing::instrument(skip(self))]
+    pub async fn find_elements(
+        &mut self,
+        query: ElementQuery,
+    ) -> Result<Vec<AccessibilityElement>, Error> {
+        txrx!(
+            self,
+            InputMessage::FindElement { query },
+            OutputMessage::FoundElements(elements) => elements
+        )
+    }
+
+    /// Returns the accessibility tree of the window.
+    #[tracing::instrument(skip(self), fields(title = window.title))]
+    pub async fn get_accessibility_tree(
+        &mut self,
+        window: &Window,
+    ) -> Result<AccessibilityElement, Error> {
+        txrx!(
+            self,
+            InputMessage::GetAccessibilityTree { window_id: window.id },
+            OutputMessage::GotAccessibilityTree(root) => *root
+        )
+    }
+}
@@ -0,0 +1,110 @@
+//! Events pushed by the driver, kept apart from replies to requests.
+use futures_util::Stream;
+
+use crate::{
+    DriverSnafu, Error, EventKind, InputMessage, OutputMessage, Session, TimestampedEvent,
+    UnexpectedDriverMessageSnafu,
+};
+
+/// Events received while awaiting replies are buffered until taken. Beyond
+/// this many, the oldest are dropped.
+const MAX_BUFFERED_EVENTS: usize = 4096;
+
+impl Session {
+    /// Ask the driver to push events of the given kinds.
+    ///
+    /// ```no_run
+    /// # async fn run(session: &mut winatep_client::Session) -> Result<(), winatep_client::Error> {
+    /// use futures_util::StreamExt;
+    /// use winatep_client::{DriverEvent, EventKind};
+    ///
+    /// session.subscribe(vec![EventKind::Focus]).await?;
+    /// let mut events = std::pin::pin!(session.events());
+    /// while let Some(event) = events.next().await {
+    ///     if let DriverEvent::FocusChanged { window } = event?.event {
+    ///         log::info!("focused: {window:?}");
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[tracing::instrument(skip(self))]
+    pub async fn subscribe(&mut self, kinds: Vec<EventKind>) -> Result<(), Error> {
+        txrx!(self, InputMessage::Subscribe(kinds), OutputMessage::DidSubscribe => ())
+    }
+
+    #[tracing::instrument(skip(self))]
+    pub async fn unsubscribe(&mut self, kinds: Vec<EventKind>) -> Result<(), Error> {
+        txrx!(self, InputMessage::Unsubscribe(kinds), OutputMessage::DidUnsubscribe => ())
+    }
+
+    /// Returns the events received so far, without waiting for more.
+    pub fn take_events(&mut self) -> Vec<TimestampedEvent> {
+        self.events.drain(..).collect()
+    }
+
+    /// Wait for the next event, returning buffered events first.
+    ///
+    /// No request may be in flight, so any reply received here is an error.
+    #[tracing::instrument(skip(self))]
+    pub async fn next_event(&mut self) -> Result<TimestampedEvent, Error> {
+        if let Some(event) = self.events.pop_front() {
+            return Ok(event);
+        }
+        match self.recv_message().await? {
+            OutputMessage::Event(event) => Ok(event),
+            OutputMessage::Error(msg) => DriverSnafu { msg }.fail(),
+            output_message => UnexpectedDriverMessageSnafu { output_message }.fail(),
+        }
+    }
+
+    /// Returns a stream of events, see [`Session::subscribe`].
+    ///
+    /// The stream borrows the session, so drop it before sending requests.
+    /// Events received while awaiting replies are not lost; they are buffered
+    /// and returned first the next time events are streamed. The stream ends
+    /// after the first error.
+    pub fn events(&mut self) -> impl Stream<Item = Result<TimestampedEvent, Error>> + '_ {
+        futures_util::stream::unfold(Some(self), |session| async move {
+            let session = session?;
+            match session.next_event().await {
+                Ok(event) => Some((Ok(event), Some(session))),
+                Err(e) => Some((Err(e), None)),
+            }
+        })
+    }
+
+    pub(crate) fn buffer_event(&mut self, event: TimestampedEvent) {
+        log::trace!("event: {:#?}", event.redacted());
+        if self.events.len() >= MAX_BUFFERED_EVENTS {
+            log::warn!("dropping the oldest buffered driver event");
+            self.events.pop_front();
+        }
+        self.events.push_back(event);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_lite::future::block_on;
+    use futures_util::StreamExt;
+
+    use super::*;
+    use crate::{mock::MockDriver, DriverEvent};
+
+    #[test]
+    fn events_end_after_an_error() {
+        let mut driver = MockDriver::new();
+        for change_count in [1, 2] {
+            driver.push_event(TimestampedEvent {
+                timestamp_in_seconds: change_count as f64,
+                event: DriverEvent::ClipboardChanged { change_count },
+            });
+        }
+        let mut session = Session::mock(driver);
+        let events = block_on(session.events().collect::<Vec<_>>());
+        assert_eq!(events.len(), 3, "{events:?}");
+        assert!(events[..2].iter().all(|e| e.is_ok()));
+        assert!(matches!(events[2], Err(Error::End)), "{:?}", events[2]);
+    }
+}
@@ -0,0 +1,49 @@
+//! Keeping track of keys and buttons held down.
+use crate::{Direction, Error, Session, Token};
+
+impl Session {
+    /// Remember which keys and buttons the token presses or releases.
+    ///
+    /// Presses are remembered before they are sent, so that a key is still
+    /// released if sending its press fails part way.
+    pub(crate) fn track_held(&mut self, token: &Token) {
+        let pressed = match token {
+            Token::Key(key, direction) => (Token::Key(*key, Direction::Press), direction),
+            Token::Raw(code, direction) => (Token::Raw(*code, Direction::Press), direction),
+            Token::Button(button, direction) => {
+                (Token::Button(button.clone(), Direction::Press), direction)
+            }
+            _ => return,
+        };
+        match pressed {
+            (press, Direction::Press) => {
+                if !self.held.contains(&press) {
+                    self.held.push(press);
+                }
+            }
+            (press, Direction::Release) => self.held.retain(|held| *held != press),
+            (_, Direction::Click) => {}
+        }
+    }
+
+    /// Returns the presses of keys and buttons that have not been released,
+    /// in the order they were pressed.
+    pub fn held_inputs(&self) -> &[Token] {
+        &self.held
+    }
+
+    /// Release every key and button still held down, most recent first, eg.
+    /// after a script failed part way through a drag.
+    ///
+    /// Every release is attempted even if some fail, and the first error is
+    /// returned.
+    #[tracing::instrument(skip(self), fields(held = self.held.len()))]
+    pub async fn release_held_inputs(&mut self) -> Result<(), Error> {
+        let mut result = Ok(());
+        while let Some(release) = self.held.last().and_then(Token::released) {
+            let released = self.input(release).await;
+            result = result.and(released);
+        }
+        result
+    }
+}
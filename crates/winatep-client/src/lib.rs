@@ -1,6 +1,9 @@
 //! Welocme to the Rust Doasif WINATEP client.
 //!
 //! TODO: Talk about the WINATEP project.
+
+// `Error` carries tungstenite's error by value, as it always has.
+#![allow(clippy::result_large_err)]
 use async_net::TcpStream;
 use async_tungstenite::{
     async_tls::ClientStream,
@@ -17,6 +20,15 @@ pub use glam::Vec2;
 use tracing::Instrument;
 pub use winatep_wire_types::*;
 
+mod annotate;
+pub use annotate::{annotate, save_annotated, Annotation};
+mod assets;
+pub use assets::*;
+mod baseline;
+pub use baseline::{compare_images, diff_image, Comparison, Tolerance};
+mod drag;
+pub use drag::DragOptions;
+
 #[derive(Debug, Snafu)]
 pub enum Error {
     #[snafu(display("{source}"))]
@@ -54,6 +66,104 @@ pub enum Error {
     #[snafu(display("Could not decode message: {source}"))]
     Decoding { source: serde_json::Error },
 
+    #[snafu(display("Could not decode asset '{path}': {source}"))]
+    DecodeAsset {
+        path: String,
+        source: image::ImageError,
+    },
+
+    #[snafu(display("No variant of the asset '{field}' is available on this OS"))]
+    MissingAsset { field: String },
+
+    #[snafu(display("Image buffer of {width}x{height} has {len} bytes, which is not RGB8"))]
+    MalformedImage { width: u32, height: u32, len: usize },
+
+    #[snafu(display("Could not create directory '{}': {source}", path.display()))]
+    CreateDir {
+        path: std::path::PathBuf,
+        source: std::io::Error,
+    },
+
+    #[snafu(display("Could not save image to '{}': {source}", path.display()))]
+    SaveImage {
+        path: std::path::PathBuf,
+        source: image::ImageError,
+    },
+
+    #[snafu(display("Could not load image from '{}': {source}", path.display()))]
+    LoadImage {
+        path: std::path::PathBuf,
+        source: image::ImageError,
+    },
+
+    #[snafu(display(
+        "Region does not match baseline '{name}' ({comparison}), see '{}'",
+        diff_path.display()
+    ))]
+    BaselineMismatch {
+        name: String,
+        comparison: Comparison,
+        diff_path: std::path::PathBuf,
+    },
+
+    #[snafu(display(
+        "No baseline '{name}' at '{}', set WINATEP_UPDATE_BASELINES=1 to store the region, see '{}'",
+        path.display(),
+        actual_path.display()
+    ))]
+    BaselineMissing {
+        name: String,
+        path: std::path::PathBuf,
+        actual_path: std::path::PathBuf,
+    },
+
+    #[snafu(display(
+        "Region of {actual:?} does not match the size of baseline '{name}', {expected:?}"
+    ))]
+    BaselineSize {
+        name: String,
+        expected: (u32, u32),
+        actual: (u32, u32),
+    },
+
+    #[snafu(display("Could not read '{}': {source}", path.display()))]
+    ReadFile {
+        path: std::path::PathBuf,
+        source: std::io::Error,
+    },
+
+    #[snafu(display("Could not write '{}': {source}", path.display()))]
+    WriteFile {
+        path: std::path::PathBuf,
+        source: std::io::Error,
+    },
+
+    #[snafu(display("Could not encode or decode '{}' as JSON: {source}", path.display()))]
+    JsonFile {
+        path: std::path::PathBuf,
+        source: serde_json::Error,
+    },
+
+    #[snafu(display(
+        "Replay diverged at sent message {index}: the transcript expected {expected}, but got {actual}"
+    ))]
+    ReplayDiverged {
+        index: usize,
+        expected: String,
+        actual: String,
+    },
+
+    #[snafu(display(
+        "The transcript's reply to sent message {index} holds redacted secrets; record it with WINATEP_TRANSCRIPT_SECRETS=1 to replay it"
+    ))]
+    ReplayRedacted { index: usize },
+
+    #[snafu(display("Could not find the {what}"))]
+    NotFound { what: String },
+
+    #[snafu(display("Timed out waiting for {what}"))]
+    Timeout { what: String },
+
     #[snafu(display("{source}"))]
     Other { source: Box<dyn std::error::Error> },
 }
@@ -76,9 +186,60 @@ macro_rules! txrx {
     }};
 }
 
+mod clipboard;
+mod element;
+pub use element::Element;
+mod events;
+mod held;
+#[cfg(test)]
+mod mock;
+mod mouse;
+pub use mouse::MouseButton;
+mod process;
+pub use process::LaunchOptions;
+mod recording;
+pub use recording::{ImageAnchor, RecordOptions, Script, ScriptStep};
+mod scroll;
+mod steps;
+#[doc(hidden)]
+pub use steps::escape_markup;
+pub use steps::{StepRecord, StepScreenshot};
+#[cfg(test)]
+mod test_util;
+mod transcript;
+mod typing;
+pub use typing::{NewlineMode, TypeMode, TypeOptions};
+mod wait;
+pub use wait::ScreenChange;
+mod window;
+
+/// Time between the positions sent by [`Session::mouse_motion`].
+const MOUSE_MOTION_STEP: std::time::Duration = std::time::Duration::from_millis(10);
+
+type DriverSocket = async_tungstenite::WebSocketStream<ClientStream<async_net::TcpStream>>;
+
+/// Where messages to the driver are sent, and replies come from.
+enum Transport {
+    WebSocket(Box<DriverSocket>),
+    Replay(transcript::Replay),
+    #[cfg(test)]
+    Mock(mock::MockDriver),
+}
+
 /// Represents a connection to the WINATEP driver.
 pub struct Session {
-    socket: async_tungstenite::WebSocketStream<ClientStream<async_net::TcpStream>>,
+    transport: Transport,
+    transcript: Option<transcript::TranscriptWriter>,
+    /// Whether transcripts record secrets, rather than digests of them.
+    transcript_secrets: bool,
+    debug_dir: Option<std::path::PathBuf>,
+    baseline_dir: std::path::PathBuf,
+    click_spacing: Option<std::time::Duration>,
+    events: std::collections::VecDeque<TimestampedEvent>,
+    /// Keys and buttons pressed but not yet released, in the order pressed.
+    held: Vec<Token>,
+    started: std::time::Instant,
+    steps: steps::StepLog,
 }
 
 /// These are the core functionalities of `Session`.
@@ -105,13 +266,69 @@ impl Session {
         let (socket, response) =
             async_tungstenite::async_tls::client_async_tls(request, tcp_stream).await?;
         log::trace!("handshake response: {response:#?}");
-        Ok(Self { socket })
+        Self::with_transport(Transport::WebSocket(Box::new(socket)))
+    }
+
+    fn with_transport(transport: Transport) -> Result<Self, Error> {
+        let debug_dir = std::env::var_os("WINATEP_DEBUG_DIR").map(std::path::PathBuf::from);
+        let baseline_dir = baseline::default_baseline_dir();
+        let transcript_secrets = transcript::secrets_requested();
+        // replaying a transcript should not append to it, or to another
+        let transcript = match &transport {
+            Transport::WebSocket(_) => std::env::var_os(transcript::TRANSCRIPT_VAR)
+                .map(|path| transcript::TranscriptWriter::create(path.into(), transcript_secrets))
+                .transpose()?,
+            _ => None,
+        };
+        Ok(Self {
+            transport,
+            transcript,
+            transcript_secrets,
+            debug_dir,
+            baseline_dir,
+            click_spacing: None,
+            events: Default::default(),
+            held: vec![],
+            started: std::time::Instant::now(),
+            steps: Default::default(),
+        })
+    }
+
+    /// Set the directory debug artifacts are saved to, eg. annotated captures
+    /// of failed waits.
+    ///
+    /// Defaults to the `WINATEP_DEBUG_DIR` environment variable, if set.
+    /// Passing `None` disables saving debug artifacts.
+    pub fn set_debug_dir(&mut self, dir: Option<std::path::PathBuf>) {
+        self.debug_dir = dir;
     }
 
-    #[tracing::instrument(skip_all)]
+    /// Receive the next reply, buffering any events pushed before it.
     async fn recv(&mut self) -> Result<OutputMessage, Error> {
-        match self
-            .socket
+        loop {
+            match self.recv_message().await? {
+                OutputMessage::Event(event) => self.buffer_event(event),
+                msg => return Ok(msg),
+            }
+        }
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn recv_message(&mut self) -> Result<OutputMessage, Error> {
+        let msg = match &mut self.transport {
+            Transport::WebSocket(socket) => Self::recv_websocket(socket).await?,
+            Transport::Replay(replay) => replay.recv()?,
+            #[cfg(test)]
+            Transport::Mock(driver) => driver.recv()?,
+        };
+        if let Some(transcript) = &mut self.transcript {
+            transcript.received(&msg)?;
+        }
+        Ok(msg)
+    }
+
+    async fn recv_websocket(socket: &mut DriverSocket) -> Result<OutputMessage, Error> {
+        match socket
             .next()
             .instrument(tracing::trace_span!("awaiting"))
             .await
@@ -145,18 +362,28 @@ impl Session {
         }
     }
 
-    #[tracing::instrument(skip(self))]
+    #[tracing::instrument(skip_all)]
     async fn send(&mut self, msg: InputMessage) -> Result<OutputMessage, Error> {
-        log::trace!("send: {msg:#?}");
-        let text = tracing::trace_span!("encoding")
-            .in_scope(|| serde_json::to_string(&msg).context(EncodingSnafu))?;
-        let bytes = Utf8Bytes::from(text);
-        self.socket
-            .send(Message::Text(bytes))
-            .instrument(tracing::trace_span!("sending"))
-            .await?;
+        log::trace!("send: {:#?}", msg.redacted());
+        if let Some(transcript) = &mut self.transcript {
+            transcript.sent(&msg)?;
+        }
+        match &mut self.transport {
+            Transport::WebSocket(socket) => {
+                let text = tracing::trace_span!("encoding")
+                    .in_scope(|| serde_json::to_string(&msg).context(EncodingSnafu))?;
+                let bytes = Utf8Bytes::from(text);
+                socket
+                    .send(Message::Text(bytes))
+                    .instrument(tracing::trace_span!("sending"))
+                    .await?;
+            }
+            Transport::Replay(replay) => replay.send(&msg)?,
+            #[cfg(test)]
+            Transport::Mock(driver) => driver.send(&msg),
+        }
         let rmsg = self.recv().await?;
-        log::trace!("recv: {rmsg:#?}");
+        log::trace!("recv: {:#?}", rmsg.redacted());
         Ok(rmsg)
     }
 
@@ -189,31 +416,98 @@ impl Session {
         txrx!(self, InputMessage::GetMouseLocation, OutputMessage::GotMouseLocation(loc) => loc)
     }
 
-    #[tracing::instrument(skip(self))]
+    #[tracing::instrument(skip_all, fields(token = ?token.redacted()))]
     pub async fn input(&mut self, token: Token) -> Result<(), Error> {
+        self.track_held(&token);
         txrx!(self, InputMessage::DoInput(token), OutputMessage::DidInput => ())
     }
 
+    #[deprecated(note = "use `Session::type_text` or `Session::type_with` instead")]
     #[tracing::instrument(skip_all)]
     pub async fn text(&mut self, text: impl AsRef<str>) -> Result<(), Error> {
-        txrx!(self, InputMessage::DoTypeText(text.as_ref().to_owned()), OutputMessage::DidTypeText => ())
+        txrx!(self, InputMessage::DoTypeText(text.as_ref().into()), OutputMessage::DidTypeText => ())
     }
 
-    #[tracing::instrument(skip(self, screen_name, text))]
+    /// Find text matching the query within a screen.
+    ///
+    /// Plain strings are matched literally, see [`TextQuery`] for case-insensitive,
+    /// regex, fuzzy and whole-word matching.
+    #[tracing::instrument(skip(self, screen_name, query))]
     pub async fn find_text_in_screen(
         &mut self,
         screen_name: impl AsRef<str>,
-        text: impl AsRef<str>,
+        query: impl Into<TextQuery>,
         timeout_in_seconds: f32,
-    ) -> Result<Vec<BoundingRectangle>, Error> {
-        txrx!(
+    ) -> Result<Vec<TextMatch>, Error> {
+        let screen_name = screen_name.as_ref();
+        let query = query.into();
+        let text = query.text.clone();
+        let (locations, mut matches) = txrx!(
             self,
             InputMessage::FindText {
-                text: text.as_ref().to_owned(),
-                screen_name: screen_name.as_ref().to_owned(),
+                query,
+                screen_name: screen_name.to_owned(),
                 timeout_in_seconds
             },
-            OutputMessage::FoundText { locations } => locations
+            OutputMessage::FoundText { locations, matches } => (locations, matches)
+        )?;
+        if matches.is_empty() {
+            // drivers that predate queries only send locations
+            for location in locations {
+                let text = self.recognized_text(screen_name, location, &text).await;
+                matches.push(TextMatch { text, location });
+            }
+        }
+        self.note_matches(
+            screen_name,
+            matches
+                .iter()
+                .map(|m| Annotation::new(m.location).with_label(&m.text)),
+        );
+        Ok(matches)
+    }
+
+    /// Read the text found at a location, for drivers that do not send it.
+    ///
+    /// Falls back to the text of the query, which is what drivers that predate
+    /// queries match literally, if the driver cannot read text either.
+    async fn recognized_text(
+        &mut self,
+        screen_name: &str,
+        location: BoundingRectangle,
+        query_text: &str,
+    ) -> String {
+        match self.read_text(screen_name, Some(location)).await {
+            Ok(lines) if !lines.is_empty() => lines
+                .iter()
+                .map(|line| line.text.as_str())
+                .collect::<Vec<_>>()
+                .join(" "),
+            Ok(_) => query_text.to_owned(),
+            Err(e) => {
+                log::debug!("could not read the text found at {location:?}: {e}");
+                query_text.to_owned()
+            }
+        }
+    }
+
+    /// Read the text within a region of a screen, as lines of words.
+    ///
+    /// The region is in pixels relative to the screen, `None` reads the whole
+    /// screen.
+    #[tracing::instrument(skip(self, screen_name))]
+    pub async fn read_text(
+        &mut self,
+        screen_name: impl AsRef<str>,
+        region: Option<BoundingRectangle>,
+    ) -> Result<Vec<RecognizedLine>, Error> {
+        txrx!(
+            self,
+            InputMessage::ReadText {
+                screen_name: screen_name.as_ref().to_owned(),
+                region
+            },
+            OutputMessage::RecognizedText { lines } => lines
         )
     }
 
@@ -225,28 +519,120 @@ impl Session {
         quality: FindImageQuality,
         filter: FindImageFilter,
     ) -> Result<Vec<BoundingRectangle>, Error> {
-        txrx!(
+        let matches = self
+            .find_scaled_image_in_screen(
+                screen_name,
+                image,
+                quality,
+                filter,
+                FindImageScales::Original,
+            )
+            .await?;
+        Ok(matches.into_iter().map(|m| m.location).collect())
+    }
+
+    /// Find an image within a screen, resizing it by each of the given scales.
+    ///
+    /// Each match reports the scale it was found at.
+    #[tracing::instrument(skip(self, screen_name))]
+    pub async fn find_scaled_image_in_screen(
+        &mut self,
+        screen_name: impl AsRef<str>,
+        image: ImageBuffer,
+        quality: FindImageQuality,
+        filter: FindImageFilter,
+        scales: FindImageScales,
+    ) -> Result<Vec<ImageMatch>, Error> {
+        self.find_image_in_region(screen_name.as_ref(), None, image, quality, filter, scales)
+            .await
+    }
+
+    /// Find an image within a region of a screen, in pixels relative to the
+    /// screen, or the whole screen if `None`.
+    pub(crate) async fn find_image_in_region(
+        &mut self,
+        screen_name: &str,
+        region: Option<BoundingRectangle>,
+        image: ImageBuffer,
+        quality: FindImageQuality,
+        filter: FindImageFilter,
+        scales: FindImageScales,
+    ) -> Result<Vec<ImageMatch>, Error> {
+        let matches = txrx!(
             self,
             InputMessage::FindImage {
-                screen_name: screen_name.as_ref().to_owned(),
+                screen_name: screen_name.to_owned(),
                 image,
                 quality,
-                filter
+                filter,
+                scales,
+                region
             },
-            OutputMessage::FoundImage { locations } => locations
-        )
+            OutputMessage::FoundImage { locations, matches } => {
+                if matches.is_empty() {
+                    locations
+                        .into_iter()
+                        .map(|location| ImageMatch {
+                            location,
+                            scale: 1.0,
+                            similarity: 1.0,
+                        })
+                        .collect()
+                } else {
+                    matches
+                }
+            }
+        )?;
+        self.note_matches(screen_name, matches.iter().map(Annotation::from));
+        Ok(matches)
+    }
+
+    /// Search the given screens for any of the given templates in one round-trip.
+    ///
+    /// Passing no screens searches every screen.
+    ///
+    /// Returns every match, each reporting which template matched on which screen.
+    #[tracing::instrument(skip(self, screens, templates))]
+    pub async fn find_any_image(
+        &mut self,
+        screens: impl IntoIterator<Item = impl AsRef<str>>,
+        templates: impl IntoIterator<Item = ImageBuffer>,
+        quality: FindImageQuality,
+        filter: FindImageFilter,
+        scales: FindImageScales,
+    ) -> Result<Vec<TemplateMatch>, Error> {
+        let matches = txrx!(
+            self,
+            InputMessage::FindAnyImage {
+                screens: screens
+                    .into_iter()
+                    .map(|name| name.as_ref().to_owned())
+                    .collect(),
+                templates: templates.into_iter().collect(),
+                quality,
+                filter,
+                scales
+            },
+            OutputMessage::FoundAnyImage { matches } => matches
+        )?;
+        for m in matches.iter() {
+            self.note_matches(&m.screen_name, [Annotation::from(m)]);
+        }
+        Ok(matches)
     }
 
     #[tracing::instrument(skip_all)]
     pub async fn get_clipboard_text(&mut self) -> Result<String, Error> {
-        txrx!(self, InputMessage::GetClipboardText, OutputMessage::GotClipboardText(text) => text)
+        txrx!(self, InputMessage::GetClipboardText, OutputMessage::GotClipboardText(text) => text.into_inner())
     }
 
+    /// Set the clipboard to the text. The text is never logged, so this is
+    /// safe to use with passwords.
     #[tracing::instrument(skip_all)]
     pub async fn set_clipboard_text(&mut self, text: impl AsRef<str>) -> Result<(), Error> {
         txrx!(
             self,
-            InputMessage::SetClipboardText(text.as_ref().to_owned()),
+            InputMessage::SetClipboardText(text.as_ref().into()),
             OutputMessage::DidSetClipboardText => ()
         )
     }
@@ -308,6 +694,10 @@ impl Session {
         self.input(Token::Key(key, Direction::Click)).await
     }
 
+    /// Type the text at once, newlines included.
+    ///
+    /// See [`Session::type_with`] to type slowly, with key events, or pressing
+    /// [`Key::Return`] for newlines.
     #[tracing::instrument(skip_all)]
     pub async fn type_text(&mut self, text: impl AsRef<str>) -> Result<(), Error> {
         self.input(Token::Text(text.as_ref().into())).await
@@ -324,88 +714,76 @@ impl Session {
             .await
     }
 
+    #[deprecated(note = "use `Session::mouse(Button::Left).press()` instead")]
     #[tracing::instrument(skip(self))]
     pub async fn mouse_left_down(&mut self) -> Result<(), Error> {
-        self.input(Token::Button(Button::Left, Direction::Press))
-            .await
+        self.mouse(Button::Left).press().await
     }
 
+    #[deprecated(note = "use `Session::mouse(Button::Left).release()` instead")]
     #[tracing::instrument(skip(self))]
     pub async fn mouse_left_up(&mut self) -> Result<(), Error> {
-        self.input(Token::Button(Button::Left, Direction::Release))
-            .await
+        self.mouse(Button::Left).release().await
     }
 
+    #[deprecated(note = "use `Session::mouse(Button::Left).click(1)` instead")]
     #[tracing::instrument(skip(self))]
     pub async fn mouse_left_click(&mut self) -> Result<(), Error> {
-        self.input(Token::Button(Button::Left, Direction::Click))
-            .await
+        self.mouse(Button::Left).click(1).await
     }
 
+    #[deprecated(note = "use `Session::mouse(Button::Left).click(2)` instead")]
     #[tracing::instrument(skip(self))]
     pub async fn mouse_left_double_click(&mut self) -> Result<(), Error> {
-        self.input(Token::Button(Button::Left, Direction::Click))
-            .await?;
-        self.pause().await;
-        self.input(Token::Button(Button::Left, Direction::Click))
-            .await?;
-        Ok(())
+        self.mouse(Button::Left).click(2).await
     }
 
+    #[deprecated(note = "use `Session::mouse(Button::Middle).press()` instead")]
     #[tracing::instrument(skip(self))]
     pub async fn mouse_middle_down(&mut self) -> Result<(), Error> {
-        self.input(Token::Button(Button::Middle, Direction::Press))
-            .await
+        self.mouse(Button::Middle).press().await
     }
 
+    #[deprecated(note = "use `Session::mouse(Button::Middle).release()` instead")]
     #[tracing::instrument(skip(self))]
     pub async fn mouse_middle_up(&mut self) -> Result<(), Error> {
-        self.input(Token::Button(Button::Middle, Direction::Release))
-            .await
+        self.mouse(Button::Middle).release().await
     }
 
+    #[deprecated(note = "use `Session::mouse(Button::Middle).click(1)` instead")]
     #[tracing::instrument(skip(self))]
     pub async fn mouse_middle_click(&mut self) -> Result<(), Error> {
-        self.input(Token::Button(Button::Middle, Direction::Click))
-            .await
+        self.mouse(Button::Middle).click(1).await
     }
 
+    #[deprecated(note = "use `Session::mouse(Button::Middle).click(2)` instead")]
     #[tracing::instrument(skip(self))]
     pub async fn mouse_middle_double_click(&mut self) -> Result<(), Error> {
-        self.input(Token::Button(Button::Middle, Direction::Click))
-            .await?;
-        self.pause().await;
-        self.input(Token::Button(Button::Middle, Direction::Click))
-            .await?;
-        Ok(())
+        self.mouse(Button::Middle).click(2).await
     }
 
+    #[deprecated(note = "use `Session::mouse(Button::Right).press()` instead")]
     #[tracing::instrument(skip(self))]
     pub async fn mouse_right_down(&mut self) -> Result<(), Error> {
-        self.input(Token::Button(Button::Right, Direction::Press))
-            .await
+        self.mouse(Button::Right).press().await
     }
 
+    #[deprecated(note = "use `Session::mouse(Button::Right).release()` instead")]
     #[tracing::instrument(skip(self))]
     pub async fn mouse_right_up(&mut self) -> Result<(), Error> {
-        self.input(Token::Button(Button::Right, Direction::Release))
-            .await
+        self.mouse(Button::Right).release().await
     }
 
+    #[deprecated(note = "use `Session::mouse(Button::Right).click(1)` instead")]
     #[tracing::instrument(skip(self))]
     pub async fn mouse_right_click(&mut self) -> Result<(), Error> {
-        self.input(Token::Button(Button::Right, Direction::Click))
-            .await
+        self.mouse(Button::Right).click(1).await
     }
 
+    #[deprecated(note = "use `Session::mouse(Button::Right).click(2)` instead")]
     #[tracing::instrument(skip(self))]
     pub async fn mouse_right_double_click(&mut self) -> Result<(), Error> {
-        self.input(Token::Button(Button::Right, Direction::Click))
-            .await?;
-        self.pause().await;
-        self.input(Token::Button(Button::Right, Direction::Click))
-            .await?;
-        Ok(())
+        self.mouse(Button::Right).click(2).await
     }
 
     /// Move the mouse along a path over some period of time.
@@ -419,34 +797,76 @@ impl Session {
         path: impl IntoIterator<Item = Vec2>,
         coordinate: Coordinate,
         time_in_seconds: f32,
+    ) -> Result<(), Error> {
+        self.mouse_motion(path, coordinate, MouseMotion::linear(time_in_seconds))
+            .await
+    }
+
+    /// Move the mouse along a path of waypoints, with the given easing, curve,
+    /// speed and jitter.
+    ///
+    /// Each position along the way is sent to the driver from here, see
+    /// [`Session::mouse_motion_on_driver`] to send the whole path at once.
+    ///
+    /// ## NOTE
+    /// On MacOS, this requires the user to allowlist the application to control their computer
+    /// through accessibility features.
+    #[tracing::instrument(skip(self, path))]
+    pub async fn mouse_motion(
+        &mut self,
+        path: impl IntoIterator<Item = Vec2>,
+        coordinate: Coordinate,
+        motion: MouseMotion,
     ) -> Result<(), Error> {
         let path = path.into_iter().collect::<Vec<_>>();
-        let list = path.iter().zip(path.iter().skip(1)).collect::<Vec<_>>();
-        let total_distance = list
-            .iter()
-            .map(|(from, to)| from.distance(**to))
-            .sum::<f32>();
-        for (from, to) in list.into_iter() {
-            let distance = from.distance(*to);
-            let percentage_of_path = distance / total_distance;
-            let spline_time =
-                std::time::Duration::from_secs_f32(time_in_seconds * percentage_of_path);
-            let start = std::time::Instant::now();
-            self.input(Token::MoveMouse(from.x as i32, from.y as i32, coordinate))
-                .await?;
-            let mut now = std::time::Instant::now();
-            while now - start < spline_time {
-                now = std::time::Instant::now();
-                let percentage_done = (now - start).as_secs_f32() / spline_time.as_secs_f32();
-                let position = from.lerp(*to, percentage_done);
-                self.input(Token::MoveMouse(
-                    position.x as i32,
-                    position.y as i32,
-                    coordinate,
-                ))
-                .await?;
+        if path.is_empty() {
+            return Ok(());
+        }
+        let trajectory = motion.trajectory(&path);
+        let duration = trajectory.duration_in_seconds();
+        // positions are taken at fixed times rather than whenever the driver
+        // replies, so the same motion always sends the same messages, eg. when
+        // replaying a transcript
+        let start = std::time::Instant::now();
+        for step in 0.. {
+            let due = MOUSE_MOTION_STEP * step;
+            if let Some(wait) = due.checked_sub(start.elapsed()) {
+                self.sleep(wait).await;
+            }
+            let elapsed = due.as_secs_f32().min(duration);
+            let position = trajectory.position_at(elapsed).round();
+            self.input(Token::MoveMouse(
+                position.x as i32,
+                position.y as i32,
+                coordinate,
+            ))
+            .await?;
+            if elapsed >= duration {
+                break;
             }
         }
         Ok(())
     }
+
+    /// Move the mouse along a path of waypoints, like [`Session::mouse_motion`],
+    /// but timed by the driver in one round-trip.
+    ///
+    /// This gives smoother motion when the connection to the driver is slow.
+    #[tracing::instrument(skip(self, path))]
+    pub async fn mouse_motion_on_driver(
+        &mut self,
+        path: impl IntoIterator<Item = Vec2>,
+        coordinate: Coordinate,
+        motion: MouseMotion,
+    ) -> Result<(), Error> {
+        txrx!(
+            self,
+            InputMessage::DoMouseMotion {
+                path: path.into_iter().collect(),
+                coordinate,
+                motion
+            },
+            OutputMessage::DidMouseMotion => ()
+        )
+    }
 }
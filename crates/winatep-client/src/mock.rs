@@ -0,0 +1,249 @@
+//! A driver that answers from a fixed scene, to test the client without a
+//! desktop.
+use std::collections::VecDeque;
+
+use snafu::prelude::*;
+
+use crate::{
+    test_util::capture, BoundingRectangle, EndSnafu, Error, InputMessage, OutputMessage,
+    RecognizedLine, RecognizedWord, Screen, Session, TimestampedEvent, Token, Transport,
+};
+
+/// The screens, and the text on them, that the mock driver answers with.
+///
+/// Every screen is captured as plain grey.
+#[derive(Default)]
+pub(crate) struct MockDriver {
+    pub(crate) screens: Vec<Screen>,
+    /// The text on each screen, by screen name.
+    pub(crate) text: Vec<(String, RecognizedLine)>,
+    /// Inputs the driver fails to send.
+    pub(crate) failing_inputs: Vec<Token>,
+    /// Every message sent to the driver, in order.
+    pub(crate) sent: Vec<InputMessage>,
+    replies: VecDeque<OutputMessage>,
+}
+
+impl MockDriver {
+    /// A scene with one 800x600 screen named `"main"`.
+    pub(crate) fn new() -> Self {
+        MockDriver {
+            screens: vec![Screen {
+                name: "main".to_owned(),
+                x: 0,
+                y: 0,
+                width: 800,
+                height: 600,
+                scale_factor: 1.0,
+            }],
+            ..Default::default()
+        }
+    }
+
+    /// Put a line of text on a screen, with each word as wide as its
+    /// characters.
+    pub(crate) fn with_line(mut self, screen_name: &str, text: &str, min: crate::Vec2) -> Self {
+        const CHAR_SIZE: crate::Vec2 = crate::Vec2::new(8.0, 16.0);
+        let mut words = vec![];
+        let mut x = min.x;
+        for word in text.split(' ') {
+            let width = word.chars().count() as f32 * CHAR_SIZE.x;
+            words.push(RecognizedWord {
+                text: word.to_owned(),
+                location: BoundingRectangle {
+                    min: crate::Vec2::new(x, min.y),
+                    max: crate::Vec2::new(x + width, min.y + CHAR_SIZE.y),
+                },
+                confidence: 1.0,
+            });
+            x += width + CHAR_SIZE.x;
+        }
+        let line = RecognizedLine {
+            text: text.to_owned(),
+            location: BoundingRectangle {
+                min,
+                max: crate::Vec2::new(x - CHAR_SIZE.x, min.y + CHAR_SIZE.y),
+            },
+            confidence: 1.0,
+            words,
+        };
+        self.text.push((screen_name.to_owned(), line));
+        self
+    }
+
+    /// Push an event, received after the replies already queued.
+    pub(crate) fn push_event(&mut self, event: TimestampedEvent) {
+        self.replies.push_back(OutputMessage::Event(event));
+    }
+
+    pub(crate) fn send(&mut self, msg: &InputMessage) {
+        self.sent.push(msg.clone());
+        let reply = self.reply(msg);
+        self.replies.push_back(reply);
+    }
+
+    pub(crate) fn recv(&mut self) -> Result<OutputMessage, Error> {
+        self.replies.pop_front().context(EndSnafu)
+    }
+
+    fn screen(&self, name: &str) -> Option<&Screen> {
+        self.screens.iter().find(|s| s.name == name)
+    }
+
+    fn reply(&self, msg: &InputMessage) -> OutputMessage {
+        match msg {
+            InputMessage::GetScreens => OutputMessage::GotScreens(self.screens.clone()),
+            InputMessage::CaptureScreen { name } => match self.screen(name) {
+                Some(screen) => OutputMessage::CapturedScreen {
+                    image_buffer: capture(
+                        (screen.width as f32 * screen.scale_factor) as u32,
+                        (screen.height as f32 * screen.scale_factor) as u32,
+                    ),
+                },
+                None => OutputMessage::Error(format!("no screen named '{name}'")),
+            },
+            InputMessage::ReadText {
+                screen_name,
+                region,
+            } => {
+                if self.screen(screen_name).is_none() {
+                    return OutputMessage::Error(format!("no screen named '{screen_name}'"));
+                }
+                let lines = self
+                    .text
+                    .iter()
+                    .filter(|(name, _)| name == screen_name)
+                    .filter_map(|(_, line)| match region {
+                        Some(region) => crop_line(line, region),
+                        None => Some(line.clone()),
+                    })
+                    .collect();
+                OutputMessage::RecognizedText { lines }
+            }
+            // answered like a driver that predates queries, with locations only
+            InputMessage::FindText {
+                query, screen_name, ..
+            } => {
+                let matcher = match query.matcher() {
+                    Ok(matcher) => matcher,
+                    Err(e) => return OutputMessage::Error(e.to_string()),
+                };
+                let locations = self
+                    .text
+                    .iter()
+                    .filter(|(name, _)| name == screen_name)
+                    .flat_map(|(_, line)| &line.words)
+                    .filter(|word| !matcher.find_all(&word.text).is_empty())
+                    .map(|word| word.location)
+                    .collect();
+                OutputMessage::FoundText {
+                    locations,
+                    matches: vec![],
+                }
+            }
+            InputMessage::DoInput(token) if self.failing_inputs.contains(token) => {
+                OutputMessage::Error(format!("could not send {token:?}"))
+            }
+            InputMessage::DoInput(_) => OutputMessage::DidInput,
+            InputMessage::StartRecording { .. } => OutputMessage::DidStartRecording,
+            InputMessage::StopRecording => OutputMessage::DidStopRecording,
+            msg => OutputMessage::Error(format!("the mock driver does not support {msg:?}")),
+        }
+    }
+}
+
+/// Returns the part of the line within the region, as if only the region had
+/// been read.
+fn crop_line(line: &RecognizedLine, region: &BoundingRectangle) -> Option<RecognizedLine> {
+    let words = line
+        .words
+        .iter()
+        .filter(|word| region.contains_point(word.location.center()))
+        .cloned()
+        .collect::<Vec<_>>();
+    let (first, last) = (words.first()?, words.last()?);
+    Some(RecognizedLine {
+        text: words
+            .iter()
+            .map(|word| word.text.as_str())
+            .collect::<Vec<_>>()
+            .join(" "),
+        location: BoundingRectangle {
+            min: first.location.min,
+            max: last.location.max,
+        },
+        confidence: line.confidence,
+        words,
+    })
+}
+
+impl Session {
+    /// Create a session connected to the mock driver.
+    pub(crate) fn mock(driver: MockDriver) -> Self {
+        Self::with_transport(Transport::Mock(driver)).expect("mock sessions do not record")
+    }
+
+    pub(crate) fn mock_driver(&mut self) -> &mut MockDriver {
+        match &mut self.transport {
+            Transport::Mock(driver) => driver,
+            _ => panic!("not a mock session"),
+        }
+    }
+}
+
+mod tests {
+    use futures_lite::future::block_on;
+
+    use super::*;
+    use crate::{TextQuery, Vec2};
+
+    fn scene() -> MockDriver {
+        MockDriver::new()
+            .with_line("main", "File Edit View", Vec2::new(10.0, 4.0))
+            .with_line("main", "Saved 3 items", Vec2::new(600.0, 580.0))
+    }
+
+    #[test]
+    fn reads_the_text_of_the_scene() {
+        let mut session = Session::mock(scene());
+        let lines = block_on(session.read_text("main", None)).unwrap();
+        let text = lines.iter().map(|l| l.text.as_str()).collect::<Vec<_>>();
+        assert_eq!(text, ["File Edit View", "Saved 3 items"]);
+        let words = lines[0]
+            .words
+            .iter()
+            .map(|w| w.text.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(words, ["File", "Edit", "View"]);
+        assert_eq!(lines[0].words[1].location.min, Vec2::new(50.0, 4.0));
+    }
+
+    #[test]
+    fn reads_only_the_text_within_the_region() {
+        let mut session = Session::mock(scene());
+        let status_bar = BoundingRectangle {
+            min: Vec2::new(0.0, 560.0),
+            max: Vec2::new(800.0, 600.0),
+        };
+        let lines = block_on(session.read_text("main", Some(status_bar))).unwrap();
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0].text, "Saved 3 items");
+    }
+
+    #[test]
+    fn found_text_from_older_drivers_is_read() {
+        let mut session = Session::mock(scene());
+        let query = TextQuery::literal("edit").case_insensitive();
+        let matches = block_on(session.find_text_in_screen("main", query, 1.0)).unwrap();
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].text, "Edit");
+        assert_eq!(matches[0].location.min, Vec2::new(50.0, 4.0));
+    }
+
+    #[test]
+    fn reading_an_unknown_screen_fails() {
+        let mut session = Session::mock(scene());
+        let result = block_on(session.read_text("side", None));
+        assert!(matches!(result, Err(Error::Driver { .. })), "{result:?}");
+    }
+}
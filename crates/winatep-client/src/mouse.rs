@@ -0,0 +1,107 @@
+//! Mouse buttons.
+use std::time::Duration;
+
+use crate::{
+    Button, Direction, DriverSnafu, Error, InputMessage, OutputMessage, Session, Token,
+    UnexpectedDriverMessageSnafu,
+};
+
+/// Time between the clicks of a multi-click for drivers that cannot report
+/// their double-click interval.
+const FALLBACK_CLICK_SPACING: Duration = Duration::from_millis(10);
+
+/// A mouse button to press, release or click, see [`Session::mouse`].
+pub struct MouseButton<'a> {
+    session: &'a mut Session,
+    button: Button,
+}
+
+impl MouseButton<'_> {
+    #[tracing::instrument(skip(self), fields(button = ?self.button))]
+    pub async fn press(self) -> Result<(), Error> {
+        self.session
+            .input(Token::Button(self.button, Direction::Press))
+            .await
+    }
+
+    #[tracing::instrument(skip(self), fields(button = ?self.button))]
+    pub async fn release(self) -> Result<(), Error> {
+        self.session
+            .input(Token::Button(self.button, Direction::Release))
+            .await
+    }
+
+    /// Click the button `count` times, eg. `2` for a double-click or `3` for a
+    /// triple-click.
+    ///
+    /// Clicks are spaced well within the system's double-click interval, so
+    /// they register as one multi-click.
+    #[tracing::instrument(skip(self), fields(button = ?self.button))]
+    pub async fn click(self, count: u32) -> Result<(), Error> {
+        let spacing = if count > 1 {
+            self.session.click_spacing().await?
+        } else {
+            Duration::ZERO
+        };
+        for i in 0..count {
+            if i > 0 {
+                self.session.sleep(spacing).await;
+            }
+            self.session
+                .input(Token::Button(self.button.clone(), Direction::Click))
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+impl Session {
+    /// Returns a mouse button to press, release or click.
+    ///
+    /// ```no_run
+    /// # async fn run(session: &mut winatep_client::Session) -> Result<(), winatep_client::Error> {
+    /// use winatep_client::Button;
+    ///
+    /// session.mouse(Button::Left).click(2).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn mouse(&mut self, button: Button) -> MouseButton<'_> {
+        MouseButton {
+            session: self,
+            button,
+        }
+    }
+
+    /// Returns the longest time between two clicks for them to count as a
+    /// double-click, as reported by the driver.
+    #[tracing::instrument(skip(self))]
+    pub async fn get_double_click_interval(&mut self) -> Result<Duration, Error> {
+        txrx!(
+            self,
+            InputMessage::GetDoubleClickInterval,
+            OutputMessage::GotDoubleClickInterval { seconds } => Duration::from_secs_f32(seconds)
+        )
+    }
+
+    /// Time between the clicks of a multi-click, a quarter of the double-click
+    /// interval, fetched from the driver once per session.
+    ///
+    /// Falls back to the spacing clicks always had for drivers that cannot
+    /// report the interval.
+    async fn click_spacing(&mut self) -> Result<Duration, Error> {
+        if let Some(spacing) = self.click_spacing {
+            return Ok(spacing);
+        }
+        let spacing = match self.get_double_click_interval().await {
+            Ok(interval) => interval / 4,
+            Err(e @ (Error::Driver { .. } | Error::UnexpectedDriverMessage { .. })) => {
+                log::warn!("driver did not report its double-click interval: {e}");
+                FALLBACK_CLICK_SPACING
+            }
+            Err(e) => return Err(e),
+        };
+        self.click_spacing = Some(spacing);
+        Ok(spacing)
+    }
+}
@@ -0,0 +1,143 @@
+//! Launching and terminating applications.
+use std::{collections::BTreeMap, time::Instant};
+
+use crate::{
+    DriverSnafu, Error, InputMessage, OutputMessage, Process, Secret, Session, TimeoutSnafu,
+    UnexpectedDriverMessageSnafu, Window,
+};
+
+/// Options for [`Session::launch_app`].
+#[derive(Clone, Debug, Default)]
+pub struct LaunchOptions {
+    /// Command line arguments passed to the app.
+    pub args: Vec<String>,
+    /// Variables added to the environment of the driver for the app. The
+    /// values are redacted from logs, since they often hold tokens.
+    pub env: BTreeMap<String, Secret<String>>,
+}
+
+impl LaunchOptions {
+    pub fn arg(mut self, arg: impl Into<String>) -> Self {
+        self.args.push(arg.into());
+        self
+    }
+
+    pub fn env(mut self, name: impl Into<String>, value: impl Into<Secret<String>>) -> Self {
+        self.env.insert(name.into(), value.into());
+        self
+    }
+}
+
+impl Session {
+    /// Launch an application and return its process ID.
+    ///
+    /// `path_or_bundle_id` is the path of the executable or app bundle, or the
+    /// bundle ID on macOS, eg. `"com.apple.TextEdit"`.
+    ///
+    /// ```no_run
+    /// # async fn run(session: &mut winatep_client::Session) -> Result<(), winatep_client::Error> {
+    /// use std::time::{Duration, Instant};
+    /// use winatep_client::LaunchOptions;
+    ///
+    /// let process_id = session
+    ///     .launch_app("/Applications/Narrative Select.app", &LaunchOptions::default())
+    ///     .await?;
+    /// let deadline = Instant::now() + Duration::from_secs(15);
+    /// let window = session.wait_for_process_window(process_id, deadline).await?;
+    /// // ...
+    /// session.terminate_process(process_id, false).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[tracing::instrument(skip(self, path_or_bundle_id))]
+    pub async fn launch_app(
+        &mut self,
+        path_or_bundle_id: impl AsRef<str>,
+        options: &LaunchOptions,
+    ) -> Result<u32, Error> {
+        txrx!(
+            self,
+            InputMessage::LaunchApp {
+                path_or_bundle_id: path_or_bundle_id.as_ref().to_owned(),
+                args: options.args.clone(),
+                env: options.env.clone(),
+            },
+            OutputMessage::LaunchedApp { process_id } => process_id
+        )
+    }
+
+    #[tracing::instrument(skip(self))]
+    pub async fn list_processes(&mut self) -> Result<Vec<Process>, Error> {
+        txrx!(
+            self,
+            InputMessage::ListProcesses,
+            OutputMessage::GotProcesses(processes) => processes
+        )
+    }
+
+    /// Returns the running processes with the given name.
+    #[tracing::instrument(skip(self, name))]
+    pub async fn find_processes(&mut self, name: impl AsRef<str>) -> Result<Vec<Process>, Error> {
+        let name = name.as_ref();
+        let mut processes = self.list_processes().await?;
+        processes.retain(|process| process.name == name);
+        Ok(processes)
+    }
+
+    /// Ask the process to quit, or kill it if `force` is set.
+    #[tracing::instrument(skip(self))]
+    pub async fn terminate_process(&mut self, process_id: u32, force: bool) -> Result<(), Error> {
+        txrx!(
+            self,
+            InputMessage::TerminateProcess { process_id, force },
+            OutputMessage::DidTerminateProcess => ()
+        )
+    }
+
+    /// Wait until the process shows a window, eg. after launching it, and
+    /// return its frontmost window.
+    #[tracing::instrument(skip(self))]
+    pub async fn wait_for_process_window(
+        &mut self,
+        process_id: u32,
+        deadline: Instant,
+    ) -> Result<Window, Error> {
+        loop {
+            let window = self
+                .list_windows()
+                .await?
+                .into_iter()
+                .find(|window| window.process_id == process_id);
+            if let Some(window) = window {
+                return Ok(window);
+            }
+            if Instant::now() >= deadline {
+                let what = format!("a window of process {process_id}");
+                log::error!("timed out waiting for {what}");
+                return TimeoutSnafu { what }.fail();
+            }
+            self.pause().await;
+        }
+    }
+
+    /// Wait until the process has exited, eg. after terminating it.
+    #[tracing::instrument(skip(self))]
+    pub async fn wait_for_process_exit(
+        &mut self,
+        process_id: u32,
+        deadline: Instant,
+    ) -> Result<(), Error> {
+        loop {
+            let processes = self.list_processes().await?;
+            if !processes.iter().any(|process| process.id == process_id) {
+                return Ok(());
+            }
+            if Instant::now() >= deadline {
+                let what = format!("process {process_id} to exit");
+                log::error!("timed out waiting for {what}");
+                return TimeoutSnafu { what }.fail();
+            }
+            self.pause().await;
+        }
+    }
+}
@@ -0,0 +1,424 @@
+//! Recording real input into a script, and playing it back.
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use snafu::prelude::*;
+
+use crate::{
+    annotate, baseline, BoundingRectangle, Coordinate, CreateDirSnafu, Direction, DriverEvent,
+    DriverSnafu, Error, ImageBuffer, InputMessage, JsonFileSnafu, Key, LoadImageSnafu,
+    MalformedImageSnafu, NotFoundSnafu, OutputMessage, ReadFileSnafu, Session, Token,
+    UnexpectedDriverMessageSnafu, Vec2, WriteFileSnafu,
+};
+
+/// A template cropped around a recorded click, so playback clicks the same
+/// thing even if it has moved.
+#[derive(Clone, Debug)]
+pub struct ImageAnchor {
+    pub screen_name: String,
+    pub template: ImageBuffer,
+    /// The click location relative to the center of the template, in pixels.
+    pub offset: Vec2,
+}
+
+/// One recorded input.
+#[derive(Clone, Debug)]
+pub struct ScriptStep {
+    /// Seconds since the first step.
+    pub timestamp_in_seconds: f64,
+    pub token: Token,
+    pub anchor: Option<ImageAnchor>,
+}
+
+/// Input recorded with [`Session::record`], to play back with
+/// [`Session::play`].
+#[derive(Clone, Debug, Default)]
+pub struct Script {
+    pub steps: Vec<ScriptStep>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct ScriptFile {
+    steps: Vec<ScriptStepFile>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct ScriptStepFile {
+    timestamp_in_seconds: f64,
+    token: Token,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    anchor: Option<ImageAnchorFile>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct ImageAnchorFile {
+    screen_name: String,
+    /// The file name of the template, next to the script.
+    template: String,
+    offset: Vec2,
+}
+
+impl Script {
+    /// Save the script as JSON, with the templates of any anchors saved as PNGs
+    /// next to it.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), Error> {
+        let path = path.as_ref();
+        let dir = path.parent().unwrap_or(Path::new(""));
+        let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+        std::fs::create_dir_all(dir).context(CreateDirSnafu { path: dir })?;
+        let mut steps = vec![];
+        for (i, step) in self.steps.iter().enumerate() {
+            let anchor = match &step.anchor {
+                Some(anchor) => {
+                    let template = format!("{stem}.anchor-{i}.png");
+                    let img = anchor
+                        .template
+                        .to_rgb_image()
+                        .context(MalformedImageSnafu {
+                            width: anchor.template.width,
+                            height: anchor.template.height,
+                            len: anchor.template.buffer.len(),
+                        })?;
+                    annotate::save_png(&img, dir.join(&template))?;
+                    Some(ImageAnchorFile {
+                        screen_name: anchor.screen_name.clone(),
+                        template,
+                        offset: anchor.offset,
+                    })
+                }
+                None => None,
+            };
+            steps.push(ScriptStepFile {
+                timestamp_in_seconds: step.timestamp_in_seconds,
+                token: step.token.clone(),
+                anchor,
+            });
+        }
+        let json =
+            serde_json::to_string_pretty(&ScriptFile { steps }).context(JsonFileSnafu { path })?;
+        std::fs::write(path, json).context(WriteFileSnafu { path })
+    }
+
+    /// Load a script saved with [`Script::save`].
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let dir = path.parent().unwrap_or(Path::new(""));
+        let json = std::fs::read_to_string(path).context(ReadFileSnafu { path })?;
+        let file: ScriptFile = serde_json::from_str(&json).context(JsonFileSnafu { path })?;
+        let mut steps = vec![];
+        for step in file.steps {
+            let anchor = match step.anchor {
+                Some(anchor) => {
+                    let template_path: PathBuf = dir.join(&anchor.template);
+                    let template = image::open(&template_path)
+                        .context(LoadImageSnafu {
+                            path: &template_path,
+                        })?
+                        .into();
+                    Some(ImageAnchor {
+                        screen_name: anchor.screen_name,
+                        template,
+                        offset: anchor.offset,
+                    })
+                }
+                None => None,
+            };
+            steps.push(ScriptStep {
+                timestamp_in_seconds: step.timestamp_in_seconds,
+                token: step.token,
+                anchor,
+            });
+        }
+        Ok(Script { steps })
+    }
+}
+
+/// Options for [`Session::record`].
+#[derive(Clone, Debug)]
+pub struct RecordOptions {
+    /// A key that ends the recording when pressed. It is not recorded.
+    pub stop_key: Option<Key>,
+    /// End the recording after this long.
+    pub timeout: Option<Duration>,
+    /// Also record every mouse move, not only those before a button or scroll
+    /// event.
+    pub all_mouse_moves: bool,
+    /// Crop a template of this many pixels square around each click, as the
+    /// screen looked when it was pressed, so playback clicks the same thing
+    /// even if it has moved. `None` records clicks at fixed locations.
+    pub anchor_size: Option<u32>,
+}
+
+impl Default for RecordOptions {
+    fn default() -> Self {
+        RecordOptions {
+            stop_key: Some(Key::Escape),
+            timeout: None,
+            all_mouse_moves: false,
+            anchor_size: None,
+        }
+    }
+}
+
+impl Session {
+    /// Record real keyboard and mouse input until the stop key is pressed or
+    /// the timeout passes, to build a script by demonstration.
+    ///
+    /// ```no_run
+    /// # async fn run(session: &mut winatep_client::Session) -> Result<(), winatep_client::Error> {
+    /// use winatep_client::RecordOptions;
+    ///
+    /// let options = RecordOptions {
+    ///     anchor_size: Some(64),
+    ///     ..Default::default()
+    /// };
+    /// let script = session.record(&options).await?;
+    /// script.save("scripts/export.json")?;
+    /// session.play(&script).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[tracing::instrument(skip(self))]
+    pub async fn record(&mut self, options: &RecordOptions) -> Result<Script, Error> {
+        txrx!(
+            self,
+            InputMessage::StartRecording {
+                stop_key: options.stop_key,
+                timeout_in_seconds: options.timeout.map(|t| t.as_secs_f32()),
+                all_mouse_moves: options.all_mouse_moves,
+                anchor_size: options.anchor_size,
+            },
+            OutputMessage::DidStartRecording => ()
+        )?;
+        match self.recorded_steps(options).await {
+            Ok(script) => Ok(script),
+            Err(e) => {
+                // the driver is still recording
+                if let Err(stop_error) = self.stop_recording().await {
+                    log::warn!("could not stop the recording: {stop_error}");
+                }
+                Err(e)
+            }
+        }
+    }
+
+    /// Collect recorded events into a script until the recording stops.
+    async fn recorded_steps(&mut self, options: &RecordOptions) -> Result<Script, Error> {
+        let mut steps = vec![];
+        let mut other_events = vec![];
+        // anchors captured by the driver as the press happened
+        let mut pending_anchor = None;
+        // where the mouse was last recorded, for drivers that predate anchors
+        let mut recorded_location = None;
+        loop {
+            let event = self.next_event().await?;
+            match event.event {
+                DriverEvent::RecordedAnchor {
+                    screen_name,
+                    region,
+                    location,
+                    image_buffer,
+                } => {
+                    pending_anchor = Some(ImageAnchor {
+                        screen_name,
+                        template: image_buffer,
+                        offset: location - region.center(),
+                    });
+                }
+                DriverEvent::Recorded(token) => {
+                    if let Token::MoveMouse(x, y, Coordinate::Abs) = token {
+                        recorded_location = Some(Vec2::new(x as f32, y as f32));
+                    }
+                    let clicked =
+                        matches!(token, Token::Button(_, Direction::Press | Direction::Click));
+                    let anchor = match options.anchor_size {
+                        Some(size) if clicked => match pending_anchor.take() {
+                            Some(anchor) => Some(anchor),
+                            None => self.capture_anchor(size, recorded_location).await?,
+                        },
+                        _ => None,
+                    };
+                    steps.push(ScriptStep {
+                        timestamp_in_seconds: event.timestamp_in_seconds,
+                        token,
+                        anchor,
+                    });
+                }
+                DriverEvent::RecordingStopped => break,
+                _ => other_events.push(event),
+            }
+        }
+        // hand back unrelated events, ahead of any received since
+        for event in other_events.into_iter().rev() {
+            self.events.push_front(event);
+        }
+        let start = steps.first().map_or(0.0, |step| step.timestamp_in_seconds);
+        for step in steps.iter_mut() {
+            step.timestamp_in_seconds -= start;
+        }
+        log::info!("recorded {} steps", steps.len());
+        Ok(Script { steps })
+    }
+
+    /// Stop a recording, eg. one left running when a [`Session::record`]
+    /// future was dropped.
+    #[tracing::instrument(skip(self))]
+    pub async fn stop_recording(&mut self) -> Result<(), Error> {
+        txrx!(self, InputMessage::StopRecording, OutputMessage::DidStopRecording => ())
+    }
+
+    /// Crop a template around the recorded click location, or around the
+    /// mouse if no location was recorded, for drivers that do not send
+    /// [`DriverEvent::RecordedAnchor`]s.
+    ///
+    /// The capture is taken after the click, so the template may show its
+    /// effects. Returns `None` if the location is not on any screen.
+    async fn capture_anchor(
+        &mut self,
+        size: u32,
+        location: Option<Vec2>,
+    ) -> Result<Option<ImageAnchor>, Error> {
+        let location = match location {
+            Some(location) => location,
+            None => self.get_mouse_location().await?,
+        };
+        let screens = self.get_screens().await?;
+        let Some(screen) = screens.iter().find(|s| s.contains_abs_point(location)) else {
+            log::warn!("click at {location} is not on any screen, not anchoring the click");
+            return Ok(None);
+        };
+        let capture = self.capture_screen(&screen.name).await?;
+        let pixel = screen.pixel_from_abs_point(location);
+        let half = Vec2::splat(size as f32 / 2.0);
+        let bounds = Vec2::new(capture.width as f32, capture.height as f32);
+        let region = BoundingRectangle {
+            min: (pixel - half).max(Vec2::ZERO).round(),
+            max: (pixel + half).min(bounds).round(),
+        };
+        let template = baseline::crop(&capture, region)?;
+        Ok(Some(ImageAnchor {
+            screen_name: screen.name.clone(),
+            template: template.into(),
+            offset: pixel - region.center(),
+        }))
+    }
+
+    /// Play a script back with its original timing.
+    ///
+    /// Anchored clicks first find their template and move the mouse onto it,
+    /// and fail if the template is not found.
+    #[tracing::instrument(skip_all, fields(steps = script.steps.len()))]
+    pub async fn play(&mut self, script: &Script) -> Result<(), Error> {
+        let started = Instant::now();
+        for (i, step) in script.steps.iter().enumerate() {
+            let due = started + Duration::from_secs_f64(step.timestamp_in_seconds.max(0.0));
+            if let Some(wait) = due.checked_duration_since(Instant::now()) {
+                self.sleep(wait).await;
+            }
+            if let Some(anchor) = &step.anchor {
+                let screen = self
+                    .get_screens()
+                    .await?
+                    .into_iter()
+                    .find(|s| s.name == anchor.screen_name)
+                    .context(NotFoundSnafu {
+                        what: format!("screen '{}'", anchor.screen_name),
+                    })?;
+                let found = self
+                    .find_scaled_image_in_screen(
+                        &screen.name,
+                        anchor.template.clone(),
+                        Default::default(),
+                        Default::default(),
+                        Default::default(),
+                    )
+                    .await?
+                    .pop()
+                    .context(NotFoundSnafu {
+                        what: format!("anchor of step {i}"),
+                    })?;
+                let pixel = found.location.center() + anchor.offset;
+                self.set_mouse_location(screen.abs_point_from_pixel(pixel), Coordinate::Abs)
+                    .await?;
+            }
+            self.input(step.token.clone()).await?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_lite::future::block_on;
+
+    use super::*;
+    use crate::{
+        mock::MockDriver,
+        test_util::{capture, rect},
+        Button, TimestampedEvent,
+    };
+
+    fn recorded(timestamp_in_seconds: f64, event: DriverEvent) -> TimestampedEvent {
+        TimestampedEvent {
+            timestamp_in_seconds,
+            event,
+        }
+    }
+
+    #[test]
+    fn anchors_survive_moves_before_the_press() {
+        let mut driver = MockDriver::new();
+        for event in [
+            recorded(
+                1.0,
+                DriverEvent::RecordedAnchor {
+                    screen_name: "main".to_owned(),
+                    region: rect((90.0, 90.0), (110.0, 110.0)),
+                    location: Vec2::new(104.0, 100.0),
+                    image_buffer: capture(20, 20),
+                },
+            ),
+            recorded(
+                1.0,
+                DriverEvent::Recorded(Token::MoveMouse(104, 100, Coordinate::Abs)),
+            ),
+            recorded(
+                1.0,
+                DriverEvent::Recorded(Token::Button(Button::Left, Direction::Press)),
+            ),
+            recorded(2.0, DriverEvent::RecordingStopped),
+        ] {
+            driver.push_event(event);
+        }
+        let mut session = Session::mock(driver);
+        let options = RecordOptions {
+            anchor_size: Some(20),
+            ..Default::default()
+        };
+        let script = block_on(session.record(&options)).unwrap();
+        assert_eq!(script.steps.len(), 2);
+        assert!(script.steps[0].anchor.is_none());
+        let anchor = script.steps[1].anchor.as_ref().unwrap();
+        assert_eq!(anchor.offset, Vec2::new(4.0, 0.0));
+        let sent = &session.mock_driver().sent;
+        assert!(!sent
+            .iter()
+            .any(|msg| matches!(msg, InputMessage::StopRecording)));
+    }
+
+    #[test]
+    fn failed_recordings_are_stopped() {
+        let mut driver = MockDriver::new();
+        driver.push_event(recorded(
+            1.0,
+            DriverEvent::Recorded(Token::MoveMouse(10, 10, Coordinate::Abs)),
+        ));
+        let mut session = Session::mock(driver);
+        let result = block_on(session.record(&RecordOptions::default()));
+        assert!(matches!(result, Err(Error::End)), "{result:?}");
+        let sent = &session.mock_driver().sent;
+        assert!(matches!(sent.last(), Some(InputMessage::StopRecording)));
+    }
+}
@@ -0,0 +1,93 @@
+//! Scrolling with the mouse wheel or trackpad.
+use std::time::Duration;
+
+use crate::{
+    Axis, DriverSnafu, Error, FindImageFilter, FindImageQuality, FindImageScales, ImageBuffer,
+    ImageMatch, InputMessage, NotFoundSnafu, OutputMessage, ScrollUnit, Session, Token,
+    UnexpectedDriverMessageSnafu, Vec2,
+};
+
+/// Time to let scrolling momentum settle before searching the screen again.
+const SCROLL_SETTLE_TIME: Duration = Duration::from_millis(150);
+
+impl Session {
+    /// Scroll by a number of lines, at the current location of the mouse.
+    ///
+    /// Positive `dx` scrolls right, positive `dy` scrolls down.
+    #[tracing::instrument(skip(self))]
+    pub async fn scroll(&mut self, dx: i32, dy: i32) -> Result<(), Error> {
+        if dx != 0 {
+            self.input(Token::Scroll(dx, Axis::Horizontal)).await?;
+        }
+        if dy != 0 {
+            self.input(Token::Scroll(dy, Axis::Vertical)).await?;
+        }
+        Ok(())
+    }
+
+    /// Scroll by lines or pixels, optionally animating the scroll like a
+    /// trackpad, at the current location of the mouse.
+    ///
+    /// Positive `delta.x` scrolls right, positive `delta.y` scrolls down.
+    ///
+    /// Not every driver supports pixel or smooth scrolling on every OS, in
+    /// which case the driver returns an error.
+    #[tracing::instrument(skip(self))]
+    pub async fn scroll_with(
+        &mut self,
+        delta: Vec2,
+        unit: ScrollUnit,
+        smooth: bool,
+    ) -> Result<(), Error> {
+        txrx!(
+            self,
+            InputMessage::DoScroll { delta, unit, smooth },
+            OutputMessage::DidScroll => ()
+        )
+    }
+
+    /// Scroll one step at a time along the axis until the template is found
+    /// within the screen, eg. to bring an item of a long list into view.
+    ///
+    /// The mouse must already be over the area to scroll. Positive steps
+    /// scroll right or down.
+    ///
+    /// Errors if the template is still not found after `max_steps`.
+    #[tracing::instrument(skip(self, screen_name, template))]
+    #[allow(clippy::too_many_arguments)]
+    pub async fn scroll_until_image_visible(
+        &mut self,
+        screen_name: impl AsRef<str>,
+        template: ImageBuffer,
+        axis: Axis,
+        step: i32,
+        max_steps: u32,
+        quality: FindImageQuality,
+        filter: FindImageFilter,
+        scales: FindImageScales,
+    ) -> Result<Vec<ImageMatch>, Error> {
+        let screen_name = screen_name.as_ref();
+        for i in 0..=max_steps {
+            if i > 0 {
+                self.input(Token::Scroll(step, axis)).await?;
+                self.sleep(SCROLL_SETTLE_TIME).await;
+            }
+            let matches = self
+                .find_scaled_image_in_screen(
+                    screen_name,
+                    template.clone(),
+                    quality,
+                    filter,
+                    scales.clone(),
+                )
+                .await?;
+            if !matches.is_empty() {
+                return Ok(matches);
+            }
+        }
+        NotFoundSnafu {
+            what: format!("image after scrolling {max_steps} steps in '{screen_name}'"),
+        }
+        .fail()
+    }
+}
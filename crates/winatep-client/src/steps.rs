@@ -0,0 +1,468 @@
+//! Named steps with before and after screenshots, and an HTML report of them.
+use std::{
+    fmt::Write as _,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+    time::{Duration, Instant},
+};
+
+use base64::Engine;
+use snafu::prelude::*;
+
+use crate::{annotate, Annotation, CreateDirSnafu, Error, ImageBuffer, Session, WriteFileSnafu};
+
+/// Width that step screenshots are scaled down to, to keep reports small.
+const THUMBNAIL_WIDTH: u32 = 1280;
+
+/// The error of a step that has not returned, eg. because it panicked or its
+/// future was dropped.
+const UNFINISHED: &str = "did not finish";
+
+/// Matches noted during a step, with the name of their screen.
+type Frame = Vec<(String, Annotation)>;
+
+/// The steps of a session, see [`Session::step`].
+#[derive(Default)]
+pub(crate) struct StepLog {
+    /// Every step so far, in the order they started.
+    records: Vec<StepRecord>,
+    /// Matches noted during each step in progress, innermost last. Shared
+    /// with each step's [`ActiveStep`].
+    active: Arc<Mutex<Vec<Frame>>>,
+    no_screenshots: bool,
+}
+
+impl StepLog {
+    fn active(&self) -> MutexGuard<'_, Vec<Frame>> {
+        self.active.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Removes the frame of a step in progress, and those of the steps nested in
+/// it, even if the step's future is dropped.
+struct ActiveStep {
+    active: Arc<Mutex<Vec<Frame>>>,
+    depth: usize,
+}
+
+impl ActiveStep {
+    /// Returns the matches noted during the step.
+    fn finish(self) -> Frame {
+        let mut active = self.active.lock().unwrap_or_else(PoisonError::into_inner);
+        let matches = active.get_mut(self.depth).map(std::mem::take);
+        active.truncate(self.depth);
+        matches.unwrap_or_default()
+    }
+}
+
+impl Drop for ActiveStep {
+    fn drop(&mut self) {
+        self.active
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .truncate(self.depth);
+    }
+}
+
+/// A step recorded by [`Session::step`].
+#[derive(Clone, Debug)]
+pub struct StepRecord {
+    pub name: String,
+    /// Number of steps this step is nested in.
+    pub depth: usize,
+    /// When the step started, since the session was created.
+    pub started_at: Duration,
+    pub duration: Duration,
+    /// The error the step failed with, if it failed. Steps that have not
+    /// returned, eg. because the scenario timed out, failed with
+    /// `"did not finish"`.
+    pub error: Option<String>,
+    /// Number of image and text matches found during the step.
+    pub matches: usize,
+    pub screenshots: Vec<StepScreenshot>,
+}
+
+/// Screenshots of one screen, taken before and after a step.
+#[derive(Clone, Debug)]
+pub struct StepScreenshot {
+    pub screen_name: String,
+    /// A PNG, scaled down, or `None` if capturing failed.
+    pub before: Option<Vec<u8>>,
+    /// A PNG, scaled down, with the matches found during the step drawn on,
+    /// or `None` if capturing failed.
+    pub after: Option<Vec<u8>>,
+}
+
+impl Session {
+    /// Run `f` as a named step, recording its timing, screenshots of every
+    /// screen before and after, and the image and text matches found during
+    /// it.
+    ///
+    /// Steps may be nested. See [`Session::write_step_report`] to view them.
+    ///
+    /// ```no_run
+    /// # async fn run(session: &mut winatep_client::Session) -> Result<(), winatep_client::Error> {
+    /// use winatep_client::ElementQuery;
+    ///
+    /// session
+    ///     .step("open projects", async |s| {
+    ///         s.element(ElementQuery::role("button").name("Projects")).click().await
+    ///     })
+    ///     .await?;
+    /// session.write_step_report("target/winatep/steps.html")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[tracing::instrument(skip(self, name, f), fields(name = name.as_ref()))]
+    pub async fn step<T, E: std::fmt::Display>(
+        &mut self,
+        name: impl AsRef<str>,
+        f: impl AsyncFnOnce(&mut Session) -> Result<T, E>,
+    ) -> Result<T, E> {
+        let name = name.as_ref();
+        log::info!("step: {name}");
+        let before = self.capture_step_screens().await;
+        let index = self.steps.records.len();
+        let depth = self.steps.active().len();
+        self.steps.records.push(StepRecord {
+            name: name.to_owned(),
+            depth,
+            started_at: self.started.elapsed(),
+            duration: Duration::ZERO,
+            // until it returns, in case it panics or is dropped on a timeout
+            error: Some(UNFINISHED.to_owned()),
+            matches: 0,
+            screenshots: vec![],
+        });
+        self.steps.active().push(vec![]);
+        let frame = ActiveStep {
+            active: self.steps.active.clone(),
+            depth,
+        };
+
+        let started = Instant::now();
+        let result = f(self).await;
+        let duration = started.elapsed();
+
+        let matches = frame.finish();
+        let after = self.capture_step_screens().await;
+        let mut screenshots = vec![];
+        for (screen_name, capture) in after {
+            let annotations = matches
+                .iter()
+                .filter(|(name, _)| *name == screen_name)
+                .map(|(_, annotation)| annotation.clone());
+            let after = capture.and_then(|capture| match annotate(&capture, annotations) {
+                Ok(img) => thumbnail_png(img),
+                Err(e) => {
+                    log::error!("could not annotate the capture of '{screen_name}': {e}");
+                    None
+                }
+            });
+            let before = before
+                .iter()
+                .find(|(name, _)| *name == screen_name)
+                .and_then(|(_, capture)| capture.as_ref())
+                .and_then(|capture| thumbnail_png(capture.to_rgb_image()?));
+            screenshots.push(StepScreenshot {
+                screen_name,
+                before,
+                after,
+            });
+        }
+
+        let record = &mut self.steps.records[index];
+        record.duration = duration;
+        record.matches = matches.len();
+        record.screenshots = screenshots;
+        record.error = match &result {
+            Ok(_) => None,
+            Err(e) => {
+                log::error!("step '{name}' failed: {e}");
+                Some(e.to_string())
+            }
+        };
+        // matches also belong to the step this one is nested in
+        if let Some(outer) = self.steps.active().last_mut() {
+            outer.extend(matches);
+        }
+        result
+    }
+
+    /// Returns every step so far, in the order they started.
+    pub fn step_records(&self) -> &[StepRecord] {
+        &self.steps.records
+    }
+
+    /// Set whether steps take screenshots, which is slow on large screens.
+    ///
+    /// Defaults to `true`.
+    pub fn set_step_screenshots(&mut self, enabled: bool) {
+        self.steps.no_screenshots = !enabled;
+    }
+
+    /// Remember matches found within a screen, to draw them onto the
+    /// screenshots of the current step.
+    pub(crate) fn note_matches(
+        &mut self,
+        screen_name: &str,
+        annotations: impl IntoIterator<Item = Annotation>,
+    ) {
+        if let Some(matches) = self.steps.active().last_mut() {
+            matches.extend(
+                annotations
+                    .into_iter()
+                    .map(|annotation| (screen_name.to_owned(), annotation)),
+            );
+        }
+    }
+
+    /// Capture every screen, logging rather than returning failures so that
+    /// screenshots never fail a step.
+    async fn capture_step_screens(&mut self) -> Vec<(String, Option<ImageBuffer>)> {
+        if self.steps.no_screenshots {
+            return vec![];
+        }
+        let screens = match self.get_screens().await {
+            Ok(screens) => screens,
+            Err(e) => {
+                log::error!("could not list screens for step screenshots: {e}");
+                return vec![];
+            }
+        };
+        let mut captures = vec![];
+        for screen in screens {
+            let capture = match self.capture_screen(&screen.name).await {
+                Ok(capture) => Some(capture),
+                Err(e) => {
+                    log::error!("could not capture '{}' for a step: {e}", screen.name);
+                    None
+                }
+            };
+            captures.push((screen.name, capture));
+        }
+        captures
+    }
+
+    /// Write a self-contained HTML report of every step so far, with a
+    /// timeline and the screenshots embedded.
+    pub fn write_step_report(&self, path: impl AsRef<Path>) -> Result<PathBuf, Error> {
+        let path = path.as_ref().to_path_buf();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).context(CreateDirSnafu { path: parent })?;
+        }
+        let html = step_report_html(&self.steps.records, self.started.elapsed());
+        std::fs::write(&path, html).context(WriteFileSnafu { path: &path })?;
+        log::info!("wrote step report to '{}'", path.display());
+        Ok(path)
+    }
+}
+
+/// Scale the image down to the thumbnail width and encode it as a PNG.
+fn thumbnail_png(img: image::RgbImage) -> Option<Vec<u8>> {
+    let img = if img.width() > THUMBNAIL_WIDTH {
+        let height = (img.height() as u64 * THUMBNAIL_WIDTH as u64 / img.width() as u64) as u32;
+        image::imageops::resize(
+            &img,
+            THUMBNAIL_WIDTH,
+            height.max(1),
+            image::imageops::FilterType::Triangle,
+        )
+    } else {
+        img
+    };
+    let mut png = vec![];
+    match img.write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png) {
+        Ok(()) => Some(png),
+        Err(e) => {
+            log::error!("could not encode a step screenshot: {e}");
+            None
+        }
+    }
+}
+
+const STYLE: &str = "
+body { font-family: -apple-system, 'Segoe UI', sans-serif; margin: 2em; color: #222; }
+.timeline { position: relative; margin: 1em 0 2em; }
+.timeline .row { position: relative; height: 1.4em; margin: 2px 0; }
+.timeline .bar { position: absolute; height: 100%; min-width: 2px; border-radius: 3px;
+  font-size: 0.8em; line-height: 1.75em; white-space: nowrap; overflow: visible; padding-left: 4px; }
+.passed .bar, .bar.passed { background: #cde8c8; }
+.failed .bar, .bar.failed { background: #f4c2c2; }
+section { border-top: 1px solid #ddd; padding: 0.5em 0; }
+section h2 { font-size: 1.1em; margin: 0.3em 0; }
+section.failed h2 { color: #b00; }
+section h2 span { font-weight: normal; color: #777; font-size: 0.85em; }
+pre { background: #fbeaea; padding: 0.5em; white-space: pre-wrap; }
+.screens { display: flex; flex-wrap: wrap; gap: 1em; }
+figure { margin: 0; }
+figure img { max-width: 40em; border: 1px solid #ccc; }
+figcaption { font-size: 0.85em; color: #555; }
+";
+
+/// Render the steps as a self-contained HTML page.
+fn step_report_html(records: &[StepRecord], total: Duration) -> String {
+    let failures = records.iter().filter(|r| r.error.is_some()).count();
+    let total_seconds = total.as_secs_f64().max(f64::EPSILON);
+    let mut html = String::new();
+    let _ = write!(
+        html,
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>WINATEP steps</title>\n<style>{STYLE}</style>\n</head>\n<body>\n\
+         <h1>Steps</h1>\n<p>{} steps, {failures} failed, {:.2}s in total</p>\n",
+        records.len(),
+        total.as_secs_f64()
+    );
+
+    html.push_str("<div class=\"timeline\">\n");
+    for (i, record) in records.iter().enumerate() {
+        let left = record.started_at.as_secs_f64() / total_seconds * 100.0;
+        let width = record.duration.as_secs_f64() / total_seconds * 100.0;
+        let _ = writeln!(
+            html,
+            "<div class=\"row {}\"><a class=\"bar\" href=\"#step-{i}\" \
+             style=\"left: {left:.2}%; width: {width:.2}%\">{}</a></div>",
+            status(record),
+            escape_markup(&record.name)
+        );
+    }
+    html.push_str("</div>\n");
+
+    for (i, record) in records.iter().enumerate() {
+        let _ = writeln!(
+            html,
+            "<section id=\"step-{i}\" class=\"{}\">\n\
+             <h2 style=\"margin-left: {}em\">{} <span>at {:.2}s, took {:.2}s, {} matches</span></h2>",
+            status(record),
+            record.depth * 2,
+            escape_markup(&record.name),
+            record.started_at.as_secs_f64(),
+            record.duration.as_secs_f64(),
+            record.matches
+        );
+        if let Some(error) = &record.error {
+            let _ = writeln!(html, "<pre>{}</pre>", escape_markup(error));
+        }
+        html.push_str("<div class=\"screens\">\n");
+        for screenshot in &record.screenshots {
+            for (when, png) in [("before", &screenshot.before), ("after", &screenshot.after)] {
+                let Some(png) = png else {
+                    continue;
+                };
+                let data = base64::engine::general_purpose::STANDARD.encode(png);
+                let _ = writeln!(
+                    html,
+                    "<figure><figcaption>{} {when}</figcaption>\
+                     <img src=\"data:image/png;base64,{data}\"></figure>",
+                    escape_markup(&screenshot.screen_name)
+                );
+            }
+        }
+        html.push_str("</div>\n</section>\n");
+    }
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+fn status(record: &StepRecord) -> &'static str {
+    if record.error.is_some() {
+        "failed"
+    } else {
+        "passed"
+    }
+}
+
+/// Escapes text for use in HTML or XML attributes and elements, eg. in
+/// reports. Not part of the API; shared with the reports of `winatep-test`.
+#[doc(hidden)]
+pub fn escape_markup(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\n' => escaped.push_str("&#10;"),
+            c if c.is_control() && c != '\t' => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_lite::future::{block_on, poll_once};
+
+    use super::*;
+
+    /// A session replaying a transcript with no messages in it.
+    fn empty_session(name: &str) -> Session {
+        let path =
+            std::env::temp_dir().join(format!("winatep-{}-{name}.jsonl", std::process::id()));
+        std::fs::write(&path, "\"Session\"\n").unwrap();
+        let mut session = Session::replay(&path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        session.set_step_screenshots(false);
+        session
+    }
+
+    #[test]
+    fn steps_record_their_outcome() {
+        let mut session = empty_session("steps");
+        block_on(session.step("passes", async |_| Ok::<_, String>(()))).unwrap();
+        block_on(session.step("fails", async |_| Err::<(), _>("oops"))).unwrap_err();
+        let errors = session
+            .step_records()
+            .iter()
+            .map(|r| r.error.as_deref())
+            .collect::<Vec<_>>();
+        assert_eq!(errors, [None, Some("oops")]);
+    }
+
+    #[test]
+    fn dropped_steps_are_unfinished() {
+        let mut session = empty_session("dropped");
+        let step = session.step("times out", async |s| {
+            s.sleep(Duration::from_secs(60)).await;
+            Ok::<_, String>(())
+        });
+        assert!(block_on(poll_once(step)).is_none());
+        let record = &session.step_records()[0];
+        assert_eq!(record.error.as_deref(), Some(UNFINISHED));
+        assert!(
+            step_report_html(session.step_records(), Duration::from_secs(1))
+                .contains("class=\"row failed\"")
+        );
+    }
+
+    #[test]
+    fn steps_after_a_dropped_step_are_not_nested() {
+        let mut session = empty_session("after-dropped");
+        let step = session.step("times out", async |s| {
+            s.step("nested", async |s| {
+                s.sleep(Duration::from_secs(60)).await;
+                Ok::<_, String>(())
+            })
+            .await
+        });
+        assert!(block_on(poll_once(step)).is_none());
+        block_on(session.step("next", async |_| Ok::<_, String>(()))).unwrap();
+        let depths = session
+            .step_records()
+            .iter()
+            .map(|r| r.depth)
+            .collect::<Vec<_>>();
+        assert_eq!(depths, [0, 1, 0]);
+    }
+
+    #[test]
+    fn markup_is_escaped() {
+        assert_eq!(
+            escape_markup("<a href=\"x\">Tom & 'Jerry'</a>\n\u{7}"),
+            "&lt;a href=&quot;x&quot;&gt;Tom &amp; &apos;Jerry&apos;&lt;/a&gt;&#10;"
+        );
+    }
+}
@@ -0,0 +1,20 @@
+//! Fixtures shared by the tests of several modules.
+use crate::{BoundingRectangle, ImageBuffer, Vec2};
+
+pub(crate) const GREY: image::Rgb<u8> = image::Rgb([100, 100, 100]);
+
+pub(crate) fn grey(width: u32, height: u32) -> image::RgbImage {
+    image::RgbImage::from_pixel(width, height, GREY)
+}
+
+/// A grey capture of a screen.
+pub(crate) fn capture(width: u32, height: u32) -> ImageBuffer {
+    grey(width, height).into()
+}
+
+pub(crate) fn rect(min: (f32, f32), max: (f32, f32)) -> BoundingRectangle {
+    BoundingRectangle {
+        min: Vec2::new(min.0, min.1),
+        max: Vec2::new(max.0, max.1),
+    }
+}
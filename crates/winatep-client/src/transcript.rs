@@ -0,0 +1,426 @@
+//! Recording the messages exchanged with the driver, and replaying them
+//! offline.
+use std::{
+    cell::Cell,
+    collections::VecDeque,
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use snafu::prelude::*;
+
+use crate::{
+    EndSnafu, Error, InputMessage, JsonFileSnafu, NotFoundSnafu, OutputMessage, ReadFileSnafu,
+    ReplayDivergedSnafu, ReplayRedactedSnafu, Session, Transport, WriteFileSnafu, REDACTED,
+};
+
+/// The environment variable naming a file to record the transcript of every
+/// session to, one after the other.
+pub(crate) const TRANSCRIPT_VAR: &str = "WINATEP_TRANSCRIPT";
+
+/// The environment variable that, when set, records secrets in transcripts
+/// as they are, so that replays can return them.
+const TRANSCRIPT_SECRETS_VAR: &str = "WINATEP_TRANSCRIPT_SECRETS";
+
+/// The environment variable holding the key secrets are digested with, so a
+/// short secret cannot be guessed from its digest without it.
+const TRANSCRIPT_KEY_VAR: &str = "WINATEP_TRANSCRIPT_KEY";
+
+pub(crate) fn secrets_requested() -> bool {
+    std::env::var(TRANSCRIPT_SECRETS_VAR)
+        .map(|value| !matches!(value.as_str(), "" | "0" | "false"))
+        .unwrap_or(false)
+}
+
+fn digest_key() -> Vec<u8> {
+    std::env::var_os(TRANSCRIPT_KEY_VAR)
+        .map(|key| key.into_encoded_bytes())
+        .unwrap_or_default()
+}
+
+/// Returns the placeholder written in place of a secret: a keyed digest of
+/// it, so replays can tell whether the same secret was sent.
+fn digest(key: &[u8], secret: &str) -> String {
+    let mac = hmac_sha256::HMAC::mac(secret, key);
+    let hex = mac[..8]
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect::<String>();
+    format!("[REDACTED:{hex}]")
+}
+
+fn is_redacted(text: &str) -> bool {
+    text == REDACTED || (text.starts_with("[REDACTED:") && text.ends_with(']'))
+}
+
+/// A line of a transcript file.
+#[derive(serde::Serialize)]
+enum TranscriptEntryRef<'a> {
+    /// The start of a session, as several may be recorded to the same file.
+    Session,
+    Sent(&'a InputMessage),
+    Received(&'a OutputMessage),
+}
+
+#[derive(serde::Deserialize)]
+enum TranscriptEntry {
+    Session,
+    Sent(InputMessage),
+    Received(OutputMessage),
+}
+
+/// Appends every message sent and received to a JSON lines file, with secrets
+/// replaced by digests unless they are recorded.
+pub(crate) struct TranscriptWriter {
+    path: PathBuf,
+    file: BufWriter<std::fs::File>,
+    key: Vec<u8>,
+    pub(crate) secrets: bool,
+}
+
+impl TranscriptWriter {
+    pub(crate) fn create(path: PathBuf, secrets: bool) -> Result<Self, Error> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).context(crate::CreateDirSnafu { path: parent })?;
+        }
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .context(WriteFileSnafu { path: &path })?;
+        log::info!("recording transcript to '{}'", path.display());
+        if secrets {
+            log::warn!("recording secrets to transcript '{}'", path.display());
+        }
+        let mut writer = TranscriptWriter {
+            path,
+            file: BufWriter::new(file),
+            key: digest_key(),
+            secrets,
+        };
+        writer.write(&TranscriptEntryRef::Session)?;
+        Ok(writer)
+    }
+
+    pub(crate) fn sent(&mut self, msg: &InputMessage) -> Result<(), Error> {
+        if self.secrets {
+            return self.write(&TranscriptEntryRef::Sent(msg));
+        }
+        let msg = msg.redacted_with(&|secret| digest(&self.key, secret));
+        self.write(&TranscriptEntryRef::Sent(&msg))
+    }
+
+    pub(crate) fn received(&mut self, msg: &OutputMessage) -> Result<(), Error> {
+        if self.secrets {
+            return self.write(&TranscriptEntryRef::Received(msg));
+        }
+        let msg = msg.redacted_with(&|secret| digest(&self.key, secret));
+        self.write(&TranscriptEntryRef::Received(&msg))
+    }
+
+    fn write(&mut self, entry: &TranscriptEntryRef) -> Result<(), Error> {
+        let path = &self.path;
+        serde_json::to_writer(&mut self.file, entry).context(JsonFileSnafu { path })?;
+        // flush every line, so the transcript is complete even if the script
+        // panics
+        writeln!(self.file)
+            .and_then(|()| self.file.flush())
+            .context(WriteFileSnafu { path })
+    }
+}
+
+/// Feeds the replies of a transcript back in order, checking that the same
+/// messages are sent.
+pub(crate) struct Replay {
+    entries: VecDeque<TranscriptEntry>,
+    /// Number of messages sent so far.
+    sent: usize,
+    key: Vec<u8>,
+}
+
+impl Replay {
+    /// Load the session at the index within the transcript, counting from 0.
+    pub(crate) fn load(path: &Path, session: usize) -> Result<Self, Error> {
+        let text = std::fs::read_to_string(path).context(ReadFileSnafu { path })?;
+        let mut sessions = vec![];
+        for line in text.lines().filter(|line| !line.trim().is_empty()) {
+            match serde_json::from_str(line).context(JsonFileSnafu { path })? {
+                TranscriptEntry::Session => sessions.push(VecDeque::new()),
+                // transcripts written before sessions were marked hold one
+                entry => match sessions.last_mut() {
+                    Some(entries) => entries.push_back(entry),
+                    None => sessions.push(VecDeque::from([entry])),
+                },
+            }
+        }
+        let count = sessions.len();
+        let entries = sessions.into_iter().nth(session).context(NotFoundSnafu {
+            what: format!(
+                "session {session} in transcript '{}', which holds {count}",
+                path.display()
+            ),
+        })?;
+        Ok(Replay {
+            entries,
+            sent: 0,
+            key: digest_key(),
+        })
+    }
+
+    pub(crate) fn send(&mut self, msg: &InputMessage) -> Result<(), Error> {
+        let index = self.sent;
+        self.sent += 1;
+        let digested = |msg: &InputMessage| msg.redacted_with(&|secret| digest(&self.key, secret));
+        let actual = digested(msg);
+        let expected = match self.entries.pop_front() {
+            Some(TranscriptEntry::Sent(expected)) => {
+                // secrets were either recorded as they are, or digested
+                let expected_value = serde_json::to_value(&expected).ok();
+                if expected_value == serde_json::to_value(msg).ok()
+                    || expected_value == serde_json::to_value(&actual).ok()
+                {
+                    return Ok(());
+                }
+                format!("{:?}", digested(&expected))
+            }
+            Some(TranscriptEntry::Received(received)) => {
+                format!("{received:?} to be received first")
+            }
+            Some(TranscriptEntry::Session) | None => "the end of the transcript".to_owned(),
+        };
+        ReplayDivergedSnafu {
+            index,
+            expected,
+            actual: format!("{actual:?}"),
+        }
+        .fail()
+    }
+
+    pub(crate) fn recv(&mut self) -> Result<OutputMessage, Error> {
+        match self.entries.pop_front() {
+            Some(TranscriptEntry::Received(msg)) => {
+                // look for placeholders among the secrets
+                let redacted = Cell::new(false);
+                msg.redacted_with(&|secret| {
+                    redacted.set(redacted.get() || is_redacted(secret));
+                    String::new()
+                });
+                ensure!(
+                    !redacted.get(),
+                    ReplayRedactedSnafu {
+                        index: self.sent.saturating_sub(1)
+                    }
+                );
+                Ok(msg)
+            }
+            Some(TranscriptEntry::Sent(expected)) => ReplayDivergedSnafu {
+                index: self.sent,
+                expected: format!("{:?}", expected.redacted()),
+                actual: "a wait for the driver".to_owned(),
+            }
+            .fail(),
+            Some(TranscriptEntry::Session) | None => EndSnafu.fail(),
+        }
+    }
+}
+
+impl Session {
+    /// Create a session that replays the first session of a transcript
+    /// recorded with [`Session::set_transcript`], rather than connecting to
+    /// the driver.
+    ///
+    /// The driver's recorded replies are fed back in order, so a script can be
+    /// re-run offline. Sending a message that differs from the recorded one
+    /// fails with [`Error::ReplayDiverged`]. Replies whose secrets were not
+    /// recorded, see [`Session::set_transcript_secrets`], fail with
+    /// [`Error::ReplayRedacted`].
+    ///
+    /// Replay sessions do not record a transcript unless
+    /// [`Session::set_transcript`] is called.
+    ///
+    /// ```no_run
+    /// # async fn run() -> Result<(), winatep_client::Error> {
+    /// let mut session = winatep_client::Session::replay("transcripts/narrative.jsonl")?;
+    /// let screens = session.get_screens().await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn replay(path: impl AsRef<Path>) -> Result<Self, Error> {
+        Self::replay_session(path, 0)
+    }
+
+    /// Like [`Session::replay`], but replays the session at the index within
+    /// the transcript, counting from 0, eg. when the transcript of a whole run
+    /// was recorded to one file.
+    pub fn replay_session(path: impl AsRef<Path>, session: usize) -> Result<Self, Error> {
+        let path = path.as_ref();
+        log::info!(
+            "replaying session {session} of transcript '{}'",
+            path.display()
+        );
+        Self::with_transport(Transport::Replay(Replay::load(path, session)?))
+    }
+
+    /// Record every message sent to and received from the driver, including
+    /// captured images, to a JSON lines file. Secrets are replaced by digests,
+    /// keyed with the `WINATEP_TRANSCRIPT_KEY` environment variable, unless
+    /// [`Session::set_transcript_secrets`] is set.
+    ///
+    /// Messages are appended, so several sessions may record to the same
+    /// file, see [`Session::replay_session`].
+    ///
+    /// Defaults to the `WINATEP_TRANSCRIPT` environment variable, if set.
+    /// Passing `None` stops recording.
+    pub fn set_transcript(&mut self, path: Option<PathBuf>) -> Result<(), Error> {
+        self.transcript = path
+            .map(|path| TranscriptWriter::create(path, self.transcript_secrets))
+            .transpose()?;
+        Ok(())
+    }
+
+    /// Record secrets, such as typed text and the clipboard, in transcripts
+    /// as they are, so that replays can return them. Only record secrets that
+    /// may be written to disk.
+    ///
+    /// Defaults to whether the `WINATEP_TRANSCRIPT_SECRETS` environment
+    /// variable is set.
+    pub fn set_transcript_secrets(&mut self, record: bool) {
+        self.transcript_secrets = record;
+        if let Some(transcript) = &mut self.transcript {
+            transcript.secrets = record;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_lite::future::block_on;
+
+    use super::*;
+    use crate::{Coordinate, MouseMotion, Token, Vec2};
+
+    const PASSWORD: &str = "hunter2";
+
+    /// The positions sent by the motion in [`script`].
+    const MOVES: [i32; 6] = [0, 20, 40, 60, 80, 100];
+
+    fn temp_path(name: &str) -> PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("winatep-{}-{name}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    /// Write a transcript of [`script`] as the driver would reply to it.
+    fn write_transcript(path: PathBuf, secrets: bool) {
+        let mut writer = TranscriptWriter::create(path, secrets).unwrap();
+        let mut exchange = |sent: InputMessage, received: OutputMessage| {
+            writer.sent(&sent).unwrap();
+            writer.received(&received).unwrap();
+        };
+        exchange(
+            InputMessage::SetClipboardText(PASSWORD.into()),
+            OutputMessage::DidSetClipboardText,
+        );
+        exchange(
+            InputMessage::GetClipboardText,
+            OutputMessage::GotClipboardText(PASSWORD.into()),
+        );
+        for x in MOVES {
+            exchange(
+                InputMessage::DoInput(Token::MoveMouse(x, 0, Coordinate::Abs)),
+                OutputMessage::DidInput,
+            );
+        }
+    }
+
+    async fn script(session: &mut Session, to: Vec2) -> Result<String, Error> {
+        session.set_clipboard_text(PASSWORD).await?;
+        let text = session.get_clipboard_text().await?;
+        session
+            .mouse_motion([Vec2::ZERO, to], Coordinate::Abs, MouseMotion::linear(0.05))
+            .await?;
+        Ok(text)
+    }
+
+    #[test]
+    fn recorded_session_replays() {
+        let original = temp_path("original");
+        let rerecorded = temp_path("rerecorded");
+        write_transcript(original.clone(), true);
+
+        let mut session = Session::replay(&original).unwrap();
+        session.set_transcript(Some(rerecorded.clone())).unwrap();
+        let text = block_on(script(&mut session, Vec2::new(100.0, 0.0))).unwrap();
+        assert_eq!(text, PASSWORD);
+        drop(session);
+
+        let text = std::fs::read_to_string(&rerecorded).unwrap();
+        assert!(!text.contains(PASSWORD));
+        for path in [original, rerecorded] {
+            std::fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[test]
+    fn redacted_replies_fail_to_replay() {
+        let path = temp_path("redacted");
+        write_transcript(path.clone(), false);
+        assert!(!std::fs::read_to_string(&path).unwrap().contains(PASSWORD));
+        let mut session = Session::replay(&path).unwrap();
+        let result = block_on(script(&mut session, Vec2::new(100.0, 0.0)));
+        assert!(
+            matches!(result, Err(Error::ReplayRedacted { index: 1 })),
+            "{result:?}"
+        );
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn changed_secrets_diverge() {
+        let path = temp_path("changed-secret");
+        write_transcript(path.clone(), false);
+        let mut session = Session::replay(&path).unwrap();
+        let result = block_on(session.set_clipboard_text("hunter3"));
+        assert!(
+            matches!(result, Err(Error::ReplayDiverged { index: 0, .. })),
+            "{result:?}"
+        );
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn replay_fails_when_the_script_diverges() {
+        let path = temp_path("diverged");
+        write_transcript(path.clone(), true);
+        let mut session = Session::replay(&path).unwrap();
+        let result = block_on(script(&mut session, Vec2::new(50.0, 0.0)));
+        assert!(
+            matches!(result, Err(Error::ReplayDiverged { index: 3, .. })),
+            "{result:?}"
+        );
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn sessions_are_appended() {
+        let path = temp_path("appended");
+        write_transcript(path.clone(), true);
+        let mut writer = TranscriptWriter::create(path.clone(), true).unwrap();
+        writer.sent(&InputMessage::GetClipboardText).unwrap();
+        writer
+            .received(&OutputMessage::GotClipboardText("second".into()))
+            .unwrap();
+        drop(writer);
+
+        let mut first = Session::replay(&path).unwrap();
+        block_on(script(&mut first, Vec2::new(100.0, 0.0))).unwrap();
+        let mut second = Session::replay_session(&path, 1).unwrap();
+        assert_eq!(block_on(second.get_clipboard_text()).unwrap(), "second");
+        assert!(matches!(
+            Session::replay_session(&path, 2),
+            Err(Error::NotFound { .. })
+        ));
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,208 @@
+//! Typing text.
+use std::time::Duration;
+
+use crate::{
+    random_unit, Direction, DriverSnafu, Error, InputMessage, Key, OutputMessage, Secret, Session,
+    Token, UnexpectedDriverMessageSnafu,
+};
+
+/// How each character is sent to the OS.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TypeMode {
+    /// Inject the text as unicode, independently of the keyboard layout.
+    #[default]
+    Unicode,
+    /// Press and release a key for each character, for apps that only listen
+    /// to key events.
+    ///
+    /// The driver maps each character to a key on the current keyboard layout.
+    KeyEvents,
+}
+
+/// How newlines in the text are typed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum NewlineMode {
+    /// Type newlines like any other character, as [`Session::type_text`] does.
+    #[default]
+    AsText,
+    /// Press [`Key::Return`] for each newline, which is what most apps expect
+    /// from a keyboard. A `'\r'` before the newline is dropped.
+    ReturnKey,
+}
+
+/// Options for [`Session::type_with`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TypeOptions {
+    /// Time to wait after each character, for apps that drop characters when
+    /// typed too fast. Zero types each line at once.
+    pub delay_per_char: Duration,
+    /// Maximum random variation of `delay_per_char`, in either direction.
+    pub jitter: Duration,
+    pub mode: TypeMode,
+    pub newlines: NewlineMode,
+    /// Seed for the jitter, so typing can be reproduced exactly.
+    pub seed: u64,
+}
+
+impl TypeOptions {
+    /// Type one character at a time, waiting the given delay after each.
+    pub fn with_delay(delay_per_char: Duration) -> Self {
+        TypeOptions {
+            delay_per_char,
+            ..Default::default()
+        }
+    }
+
+    /// The delay after the character at the given index.
+    fn delay(&self, index: u64) -> Duration {
+        if self.jitter.is_zero() {
+            return self.delay_per_char;
+        }
+        let unit = random_unit(self.seed, index);
+        let delay = self.delay_per_char.as_secs_f32() + unit * self.jitter.as_secs_f32();
+        Duration::from_secs_f32(delay.max(0.0))
+    }
+}
+
+impl Session {
+    /// Type the text with the given options.
+    ///
+    /// ```no_run
+    /// # async fn run(session: &mut winatep_client::Session) -> Result<(), winatep_client::Error> {
+    /// use std::time::Duration;
+    ///
+    /// use winatep_client::{NewlineMode, TypeOptions};
+    ///
+    /// let slowly = TypeOptions {
+    ///     newlines: NewlineMode::ReturnKey,
+    ///     ..TypeOptions::with_delay(Duration::from_millis(50))
+    /// };
+    /// session.type_with("Narrative Select\n", &slowly).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[tracing::instrument(skip(self, text))]
+    pub async fn type_with(
+        &mut self,
+        text: impl AsRef<str>,
+        options: &TypeOptions,
+    ) -> Result<(), Error> {
+        let text = text.as_ref();
+        let lines = match options.newlines {
+            NewlineMode::ReturnKey => text
+                .split('\n')
+                .map(|line| line.strip_suffix('\r').unwrap_or(line))
+                .collect::<Vec<_>>(),
+            NewlineMode::AsText => vec![text],
+        };
+        let mut index = 0;
+        for (i, line) in lines.into_iter().enumerate() {
+            if i > 0 {
+                self.key_click(Key::Return).await?;
+                self.sleep(options.delay(index)).await;
+                index += 1;
+            }
+            if options.delay_per_char.is_zero() && options.mode == TypeMode::Unicode {
+                if !line.is_empty() {
+                    self.input(Token::Text(line.to_owned())).await?;
+                }
+                continue;
+            }
+            for c in line.chars() {
+                let token = match options.mode {
+                    TypeMode::Unicode => Token::Text(c.to_string()),
+                    TypeMode::KeyEvents => Token::Key(Key::Unicode(c), Direction::Click),
+                };
+                self.input(token).await?;
+                self.sleep(options.delay(index)).await;
+                index += 1;
+            }
+        }
+        Ok(())
+    }
+
+    /// Type a secret, such as a password, at once.
+    ///
+    /// Unlike [`Session::type_text`], the text never appears in logs or traces.
+    ///
+    /// ```no_run
+    /// # async fn run(session: &mut winatep_client::Session) -> Result<(), winatep_client::Error> {
+    /// use winatep_client::Secret;
+    ///
+    /// let password = Secret::new(std::env::var("LOGIN_PASSWORD").unwrap());
+    /// session.type_secret(&password).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[tracing::instrument(skip_all)]
+    pub async fn type_secret(&mut self, secret: &Secret<String>) -> Result<(), Error> {
+        txrx!(
+            self,
+            InputMessage::DoTypeText(secret.clone()),
+            OutputMessage::DidTypeText => ()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_lite::future::block_on;
+
+    use super::*;
+    use crate::mock::MockDriver;
+
+    fn sent_tokens(session: &mut Session) -> Vec<Token> {
+        session
+            .mock_driver()
+            .sent
+            .iter()
+            .filter_map(|msg| match msg {
+                InputMessage::DoInput(token) => Some(token.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn type_text_sends_the_whole_text() {
+        let mut session = Session::mock(MockDriver::new());
+        block_on(session.type_text("one\r\ntwo")).unwrap();
+        assert_eq!(
+            sent_tokens(&mut session),
+            [Token::Text("one\r\ntwo".to_owned())]
+        );
+    }
+
+    #[test]
+    fn newlines_are_typed_as_text_by_default() {
+        let mut session = Session::mock(MockDriver::new());
+        let options = TypeOptions {
+            mode: TypeMode::KeyEvents,
+            ..Default::default()
+        };
+        block_on(session.type_with("a\nb", &options)).unwrap();
+        assert_eq!(
+            sent_tokens(&mut session),
+            ['a', '\n', 'b'].map(|c| Token::Key(Key::Unicode(c), Direction::Click))
+        );
+    }
+
+    #[test]
+    fn newlines_can_press_return() {
+        let mut session = Session::mock(MockDriver::new());
+        let options = TypeOptions {
+            newlines: NewlineMode::ReturnKey,
+            ..Default::default()
+        };
+        block_on(session.type_with("one\r\ntwo\n", &options)).unwrap();
+        assert_eq!(
+            sent_tokens(&mut session),
+            [
+                Token::Text("one".to_owned()),
+                Token::Key(Key::Return, Direction::Click),
+                Token::Text("two".to_owned()),
+                Token::Key(Key::Return, Direction::Click),
+            ]
+        );
+    }
+}
@@ -0,0 +1,307 @@
+//! Polling for changes on screen, with deadlines.
+use std::time::{Duration, Instant};
+
+use snafu::prelude::*;
+
+use crate::{
+    annotate::{self, Annotation},
+    baseline, compare_images, BoundingRectangle, DriverSnafu, Error, FindImageFilter,
+    FindImageQuality, FindImageScales, ImageBuffer, InputMessage, OutputMessage, Session,
+    TemplateMatch, TimeoutSnafu, Tolerance, UnexpectedDriverMessageSnafu,
+};
+
+/// The filter used to find the closest candidates when a search fails.
+///
+/// Returns `2^2` findings, regardless of their similarity.
+const CLOSEST_CANDIDATES_FILTER: FindImageFilter = FindImageFilter::Specific {
+    level: 2,
+    minimum_similarity: 0.0,
+};
+
+/// Captures taken before and after waiting for something on screen to change.
+#[derive(Clone, Debug)]
+pub struct ScreenChange {
+    pub before: ImageBuffer,
+    pub after: ImageBuffer,
+}
+
+impl Session {
+    /// Wait until a region of a screen stops changing, eg. once an animation
+    /// has finished after a click.
+    ///
+    /// Returns once the region has not changed for `quiet_period`, or errors
+    /// once the deadline passes. The region is in pixels relative to the screen,
+    /// `None` waits for the whole screen.
+    ///
+    /// The driver does the capturing, so frames are not sent over the socket.
+    /// See [`Session::wait_for_stable_capture`] to get the stable region.
+    #[tracing::instrument(skip(self, screen_name))]
+    pub async fn wait_for_stable(
+        &mut self,
+        screen_name: impl AsRef<str>,
+        region: Option<BoundingRectangle>,
+        quiet_period: Duration,
+        deadline: Instant,
+    ) -> Result<(), Error> {
+        self.send_wait_for_stable(screen_name.as_ref(), region, quiet_period, deadline, false)
+            .await?;
+        Ok(())
+    }
+
+    /// Like [`Session::wait_for_stable`], but returns the capture of the
+    /// stable region.
+    #[tracing::instrument(skip(self, screen_name))]
+    pub async fn wait_for_stable_capture(
+        &mut self,
+        screen_name: impl AsRef<str>,
+        region: Option<BoundingRectangle>,
+        quiet_period: Duration,
+        deadline: Instant,
+    ) -> Result<ImageBuffer, Error> {
+        let capture = self
+            .send_wait_for_stable(screen_name.as_ref(), region, quiet_period, deadline, true)
+            .await?;
+        capture.context(UnexpectedDriverMessageSnafu {
+            output_message: OutputMessage::ScreenStable { image_buffer: None },
+        })
+    }
+
+    async fn send_wait_for_stable(
+        &mut self,
+        screen_name: &str,
+        region: Option<BoundingRectangle>,
+        quiet_period: Duration,
+        deadline: Instant,
+        return_capture: bool,
+    ) -> Result<Option<ImageBuffer>, Error> {
+        txrx!(
+            self,
+            InputMessage::WaitForStable {
+                screen_name: screen_name.to_owned(),
+                region,
+                quiet_period_in_seconds: quiet_period.as_secs_f32(),
+                timeout_in_seconds: deadline
+                    .saturating_duration_since(Instant::now())
+                    .as_secs_f32(),
+                return_capture,
+            },
+            OutputMessage::ScreenStable { image_buffer } => image_buffer
+        )
+    }
+
+    /// Repeatedly search the given screens for any of the given templates until
+    /// at least one is found or the deadline passes.
+    ///
+    /// Passing no screens searches every screen.
+    ///
+    /// If the deadline passes and a debug directory is set, an annotated capture
+    /// of each screen showing the closest candidates is saved there. See
+    /// [`Session::set_debug_dir`].
+    #[tracing::instrument(skip(self, screens, templates))]
+    pub async fn wait_for_any_image(
+        &mut self,
+        screens: impl IntoIterator<Item = impl AsRef<str>>,
+        templates: impl IntoIterator<Item = ImageBuffer>,
+        quality: FindImageQuality,
+        filter: FindImageFilter,
+        scales: FindImageScales,
+        deadline: Instant,
+    ) -> Result<Vec<TemplateMatch>, Error> {
+        let screens = screens
+            .into_iter()
+            .map(|name| name.as_ref().to_owned())
+            .collect::<Vec<_>>();
+        let templates = templates.into_iter().collect::<Vec<_>>();
+        loop {
+            let matches = self
+                .find_any_image(
+                    &screens,
+                    templates.iter().cloned(),
+                    quality,
+                    filter,
+                    scales.clone(),
+                )
+                .await?;
+            if !matches.is_empty() {
+                return Ok(matches);
+            }
+            if Instant::now() >= deadline {
+                break;
+            }
+            self.pause().await;
+        }
+
+        let what = format!("any of {} images", templates.len());
+        log::error!("timed out waiting for {what}");
+        if let Err(e) = self
+            .save_closest_candidates(&screens, &templates, quality, scales)
+            .await
+        {
+            log::warn!("could not save debug captures: {e}");
+        }
+        TimeoutSnafu { what }.fail()
+    }
+
+    /// Capture each screen and annotate it with the closest candidates for each
+    /// template, if a debug directory is set.
+    async fn save_closest_candidates(
+        &mut self,
+        screens: &[String],
+        templates: &[ImageBuffer],
+        quality: FindImageQuality,
+        scales: FindImageScales,
+    ) -> Result<(), Error> {
+        if self.debug_dir.is_none() {
+            return Ok(());
+        }
+        let screens = if screens.is_empty() {
+            self.get_screens()
+                .await?
+                .into_iter()
+                .map(|screen| screen.name)
+                .collect()
+        } else {
+            screens.to_vec()
+        };
+        let candidates = self
+            .find_any_image(
+                &screens,
+                templates.iter().cloned(),
+                quality,
+                CLOSEST_CANDIDATES_FILTER,
+                scales,
+            )
+            .await?;
+        for screen_name in screens.iter() {
+            let annotations = candidates
+                .iter()
+                .filter(|m| &m.screen_name == screen_name)
+                .map(Annotation::from);
+            self.save_debug_capture(screen_name, "not-found", annotations)
+                .await;
+        }
+        Ok(())
+    }
+
+    /// Repeatedly search a screen for a template until it is no longer found,
+    /// eg. to wait for a spinner to go away.
+    ///
+    /// Returns captures of the whole screen from before waiting and from once
+    /// the template was gone.
+    ///
+    /// If the deadline passes and a debug directory is set, an annotated capture
+    /// showing where the template is still found is saved there.
+    #[tracing::instrument(skip(self, screen_name, template))]
+    pub async fn wait_until_gone(
+        &mut self,
+        screen_name: impl AsRef<str>,
+        template: ImageBuffer,
+        quality: FindImageQuality,
+        filter: FindImageFilter,
+        scales: FindImageScales,
+        deadline: Instant,
+    ) -> Result<ScreenChange, Error> {
+        let screen_name = screen_name.as_ref();
+        let before = self.capture_screen(screen_name).await?;
+        loop {
+            let matches = self
+                .find_scaled_image_in_screen(
+                    screen_name,
+                    template.clone(),
+                    quality,
+                    filter,
+                    scales.clone(),
+                )
+                .await?;
+            if matches.is_empty() {
+                let after = self.capture_screen(screen_name).await?;
+                return Ok(ScreenChange { before, after });
+            }
+            if Instant::now() >= deadline {
+                let what = format!("image to disappear from '{screen_name}'");
+                log::error!("timed out waiting for {what}");
+                let annotations = matches
+                    .iter()
+                    .map(|m| Annotation::from(m).with_label("still here"));
+                self.save_debug_capture(screen_name, "still-found", annotations)
+                    .await;
+                return TimeoutSnafu { what }.fail();
+            }
+            self.pause().await;
+        }
+    }
+
+    /// Repeatedly capture a region of a screen until it differs from how it
+    /// looked when first called, eg. to wait for a dialog to appear.
+    ///
+    /// The region is in pixels relative to the screen, `None` watches the whole
+    /// screen. Differences within the tolerance are not considered a change.
+    ///
+    /// Returns captures of the region from before and after the change.
+    ///
+    /// If the deadline passes and a debug directory is set, an annotated capture
+    /// showing the unchanged region is saved there.
+    #[tracing::instrument(skip(self, screen_name))]
+    pub async fn wait_for_change(
+        &mut self,
+        screen_name: impl AsRef<str>,
+        region: Option<BoundingRectangle>,
+        tolerance: Tolerance,
+        deadline: Instant,
+    ) -> Result<ScreenChange, Error> {
+        let screen_name = screen_name.as_ref();
+        let capture_region = |capture: ImageBuffer| match region {
+            Some(region) => baseline::crop(&capture, region).map(ImageBuffer::from),
+            None => Ok(capture),
+        };
+        let before = capture_region(self.capture_screen(screen_name).await?)?;
+        let before_img = before.to_rgb_image();
+        loop {
+            self.pause().await;
+            let after = capture_region(self.capture_screen(screen_name).await?)?;
+            let unchanged = match (&before_img, after.to_rgb_image()) {
+                (Some(before_img), Some(after_img)) => {
+                    compare_images(before_img, &after_img, tolerance).is_some_and(|c| c.passed)
+                }
+                _ => false,
+            };
+            if !unchanged {
+                return Ok(ScreenChange { before, after });
+            }
+            if Instant::now() >= deadline {
+                let what = format!("region of '{screen_name}' to change");
+                log::error!("timed out waiting for {what}");
+                let annotations = region.map(|r| Annotation::new(r).with_label("unchanged"));
+                self.save_debug_capture(screen_name, "unchanged", annotations)
+                    .await;
+                return TimeoutSnafu { what }.fail();
+            }
+        }
+    }
+
+    /// Capture the screen and save it annotated, if a debug directory is set.
+    ///
+    /// Failures are logged rather than returned, as this is only ever called
+    /// while already handling a failure.
+    async fn save_debug_capture(
+        &mut self,
+        screen_name: &str,
+        what: &str,
+        annotations: impl IntoIterator<Item = Annotation>,
+    ) {
+        let Some(debug_dir) = self.debug_dir.clone() else {
+            return;
+        };
+        let path = debug_dir.join(annotate::debug_file_name(
+            &format!("{screen_name}-{what}"),
+            "png",
+        ));
+        match self
+            .save_annotated_capture(screen_name, annotations, path)
+            .await
+        {
+            Ok(path) => log::info!("saved debug capture to '{}'", path.display()),
+            Err(e) => log::warn!("could not save debug capture: {e}"),
+        }
+    }
+}
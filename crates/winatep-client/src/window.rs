@@ -0,0 +1,128 @@
+//! Listing and managing application windows.
+use snafu::prelude::*;
+
+use crate::{
+    DriverSnafu, Error, FindImageFilter, FindImageQuality, FindImageScales, ImageBuffer,
+    ImageMatch, InputMessage, NotFoundSnafu, OutputMessage, Session, TextQuery,
+    UnexpectedDriverMessageSnafu, Vec2, Window,
+};
+
+impl Session {
+    /// Returns the open windows of every application, frontmost first.
+    #[tracing::instrument(skip(self))]
+    pub async fn list_windows(&mut self) -> Result<Vec<Window>, Error> {
+        txrx!(self, InputMessage::ListWindows, OutputMessage::GotWindows(windows) => windows)
+    }
+
+    /// Returns the frontmost window whose title matches the query.
+    ///
+    /// ```no_run
+    /// # async fn run(session: &mut winatep_client::Session) -> Result<(), winatep_client::Error> {
+    /// use winatep_client::TextQuery;
+    ///
+    /// let window = session.window(TextQuery::literal("Narrative").case_insensitive()).await?;
+    /// session.focus_window(&window).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[tracing::instrument(skip(self, title))]
+    pub async fn window(&mut self, title: impl Into<TextQuery>) -> Result<Window, Error> {
+        let title = title.into();
+        let matcher = title.matcher().map_err(|e| Error::Other {
+            source: Box::new(e),
+        })?;
+        self.list_windows()
+            .await?
+            .into_iter()
+            .find(|window| matcher.is_match(&window.title))
+            .context(NotFoundSnafu {
+                what: format!("window titled '{}'", title.text),
+            })
+    }
+
+    /// Bring the window to the front and give it keyboard focus, restoring it
+    /// if minimized.
+    #[tracing::instrument(skip(self), fields(title = window.title))]
+    pub async fn focus_window(&mut self, window: &Window) -> Result<(), Error> {
+        txrx!(
+            self,
+            InputMessage::FocusWindow { window_id: window.id },
+            OutputMessage::DidFocusWindow => ()
+        )
+    }
+
+    /// Move the top-left corner of the window to the location, in absolute
+    /// logical coordinates.
+    #[tracing::instrument(skip(self), fields(title = window.title))]
+    pub async fn move_window(&mut self, window: &Window, location: Vec2) -> Result<(), Error> {
+        txrx!(
+            self,
+            InputMessage::MoveWindow { window_id: window.id, location },
+            OutputMessage::DidMoveWindow => ()
+        )
+    }
+
+    /// Resize the window, in logical coordinates.
+    #[tracing::instrument(skip(self), fields(title = window.title))]
+    pub async fn resize_window(&mut self, window: &Window, size: Vec2) -> Result<(), Error> {
+        txrx!(
+            self,
+            InputMessage::ResizeWindow { window_id: window.id, size },
+            OutputMessage::DidResizeWindow => ()
+        )
+    }
+
+    #[tracing::instrument(skip(self), fields(title = window.title))]
+    pub async fn minimize_window(&mut self, window: &Window) -> Result<(), Error> {
+        txrx!(
+            self,
+            InputMessage::MinimizeWindow { window_id: window.id },
+            OutputMessage::DidMinimizeWindow => ()
+        )
+    }
+
+    /// Ask the window to close, as if its close button was clicked. The app
+    /// may still show a confirmation dialog.
+    #[tracing::instrument(skip(self), fields(title = window.title))]
+    pub async fn close_window(&mut self, window: &Window) -> Result<(), Error> {
+        txrx!(
+            self,
+            InputMessage::CloseWindow { window_id: window.id },
+            OutputMessage::DidCloseWindow => ()
+        )
+    }
+
+    /// Find an image within a window, rather than its whole screen, so that
+    /// look-alikes in other windows are ignored.
+    ///
+    /// The driver only searches the window's region, so look-alikes elsewhere
+    /// do not use up its matches.
+    ///
+    /// Match locations are in pixels relative to the window's screen, as with
+    /// [`Session::find_scaled_image_in_screen`].
+    #[tracing::instrument(skip(self, image), fields(title = window.title))]
+    pub async fn find_image_in_window(
+        &mut self,
+        window: &Window,
+        image: ImageBuffer,
+        quality: FindImageQuality,
+        filter: FindImageFilter,
+        scales: FindImageScales,
+    ) -> Result<Vec<ImageMatch>, Error> {
+        let screen = self
+            .get_screens()
+            .await?
+            .into_iter()
+            .find(|screen| screen.name == window.screen_name)
+            .context(NotFoundSnafu {
+                what: format!("screen '{}'", window.screen_name),
+            })?;
+        let region = window.pixel_region(&screen);
+        let mut matches = self
+            .find_image_in_region(&screen.name, Some(region), image, quality, filter, scales)
+            .await?;
+        // drivers that predate regions search the whole screen
+        matches.retain(|m| region.contains_point(m.location.center()));
+        Ok(matches)
+    }
+}
@@ -0,0 +1,165 @@
+//! `#[derive(Assets)]`.
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{
+    parse::{Parse, ParseStream},
+    Data, DeriveInput, Fields, Ident, LitFloat, LitStr, Token,
+};
+
+/// Values of `std::env::consts::OS` that an asset variant may be restricted to.
+const KNOWN_OSES: &[&str] = &[
+    "linux",
+    "macos",
+    "ios",
+    "freebsd",
+    "dragonfly",
+    "netbsd",
+    "openbsd",
+    "solaris",
+    "android",
+    "windows",
+];
+
+/// A single `#[asset("path", scale = 2.0, os = "macos")]` attribute.
+struct AssetAttr {
+    path: LitStr,
+    scale_factor: Option<LitFloat>,
+    os: Option<LitStr>,
+}
+
+impl Parse for AssetAttr {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let path: LitStr = input.parse()?;
+        let mut scale_factor = None;
+        let mut os = None;
+        while !input.is_empty() {
+            input.parse::<Token![,]>()?;
+            if input.is_empty() {
+                break;
+            }
+            let key: Ident = input.parse()?;
+            input.parse::<Token![=]>()?;
+            match key.to_string().as_str() {
+                "scale" => {
+                    scale_factor = Some(input.parse()?);
+                }
+                "os" => {
+                    let lit: LitStr = input.parse()?;
+                    if !KNOWN_OSES.contains(&lit.value().as_str()) {
+                        return Err(syn::Error::new(
+                            lit.span(),
+                            format!("unknown os, expected one of {KNOWN_OSES:?}"),
+                        ));
+                    }
+                    os = Some(lit);
+                }
+                _ => {
+                    return Err(syn::Error::new(
+                        key.span(),
+                        "unknown asset option, expected `scale` or `os`",
+                    ))
+                }
+            }
+        }
+        Ok(AssetAttr {
+            path,
+            scale_factor,
+            os,
+        })
+    }
+}
+
+impl AssetAttr {
+    /// Checks that the file exists, relative to the deriving crate's manifest.
+    fn check_exists(&self) -> syn::Result<()> {
+        let manifest_dir = std::env::var("CARGO_MANIFEST_DIR")
+            .map_err(|_| syn::Error::new(self.path.span(), "CARGO_MANIFEST_DIR is not set"))?;
+        let path = std::path::Path::new(&manifest_dir).join(self.path.value());
+        if path.is_file() {
+            Ok(())
+        } else {
+            Err(syn::Error::new(
+                self.path.span(),
+                format!("asset file not found: '{}'", path.display()),
+            ))
+        }
+    }
+
+    fn to_variant(&self) -> TokenStream {
+        let path = &self.path;
+        let os = match &self.os {
+            Some(os) => quote!(::core::option::Option::Some(#os)),
+            None => quote!(::core::option::Option::None),
+        };
+        let scale_factor = match &self.scale_factor {
+            Some(scale) => quote!(::core::option::Option::Some(#scale as f32)),
+            None => quote!(::core::option::Option::None),
+        };
+        quote! {
+            ::winatep_client::AssetVariant {
+                path: #path,
+                os: #os,
+                scale_factor: #scale_factor,
+                bytes: ::core::include_bytes!(
+                    ::core::concat!(::core::env!("CARGO_MANIFEST_DIR"), "/", #path)
+                ),
+            }
+        }
+    }
+}
+
+pub(crate) fn derive(input: DeriveInput) -> syn::Result<TokenStream> {
+    let name = &input.ident;
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    name,
+                    "Assets can only be derived for structs with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(syn::Error::new_spanned(
+                name,
+                "Assets can only be derived for structs",
+            ))
+        }
+    };
+
+    let mut field_inits = vec![];
+    for field in fields.iter() {
+        let ident = field.ident.as_ref().expect("named field");
+        let mut variants = vec![];
+        for attr in field.attrs.iter().filter(|a| a.path().is_ident("asset")) {
+            let asset: AssetAttr = attr.parse_args()?;
+            asset.check_exists()?;
+            variants.push(asset.to_variant());
+        }
+        if variants.is_empty() {
+            return Err(syn::Error::new_spanned(
+                ident,
+                "missing #[asset(\"path\")] attribute",
+            ));
+        }
+        let field_name = ident.to_string();
+        field_inits.push(quote! {
+            #ident: ::winatep_client::FromAssetVariants::from_variants(
+                #field_name,
+                &[#(#variants),*],
+            )?
+        });
+    }
+
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::winatep_client::Assets for #name #ty_generics #where_clause {
+            fn load() -> ::core::result::Result<Self, ::winatep_client::Error> {
+                ::core::result::Result::Ok(Self {
+                    #(#field_inits),*
+                })
+            }
+        }
+    })
+}
@@ -0,0 +1,32 @@
+//! Procedural macros for the WINATEP client.
+//!
+//! These are re-exported by `winatep-client` and `winatep-test`, use them
+//! from there.
+use proc_macro::TokenStream;
+use syn::{parse_macro_input, DeriveInput, ItemFn};
+
+mod assets;
+mod scenario;
+
+/// Embeds image assets at compile time.
+///
+/// See `winatep_client::Assets` for details.
+#[proc_macro_derive(Assets, attributes(asset))]
+pub fn derive_assets(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    assets::derive(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Registers an async fn as a scenario for the `winatep-test` runner.
+///
+/// See `winatep_test` for details.
+#[proc_macro_attribute]
+pub fn test(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let attr = parse_macro_input!(attr as scenario::ScenarioAttr);
+    let item = parse_macro_input!(item as ItemFn);
+    scenario::expand(attr, item)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
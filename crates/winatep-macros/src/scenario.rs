@@ -0,0 +1,102 @@
+//! `#[winatep_test::test]`.
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{
+    parse::{Parse, ParseStream},
+    Ident, ItemFn, Lit, Token,
+};
+
+/// The options of a `#[winatep_test::test(timeout = 60)]` attribute.
+#[derive(Default)]
+pub struct ScenarioAttr {
+    timeout: Option<Lit>,
+}
+
+impl Parse for ScenarioAttr {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut attr = ScenarioAttr::default();
+        while !input.is_empty() {
+            let key: Ident = input.parse()?;
+            input.parse::<Token![=]>()?;
+            match key.to_string().as_str() {
+                "timeout" => {
+                    if input.peek(Token![-]) {
+                        return Err(input.error("expected a positive timeout in seconds"));
+                    }
+                    let lit: Lit = input.parse()?;
+                    let seconds = match &lit {
+                        Lit::Int(int) => int.base10_parse::<f32>()?,
+                        Lit::Float(float) => float.base10_parse::<f32>()?,
+                        _ => {
+                            return Err(syn::Error::new_spanned(
+                                lit,
+                                "expected a timeout in seconds",
+                            ))
+                        }
+                    };
+                    if !(seconds.is_finite() && seconds > 0.0) {
+                        return Err(syn::Error::new_spanned(
+                            lit,
+                            "expected a positive timeout in seconds",
+                        ));
+                    }
+                    attr.timeout = Some(lit);
+                }
+                _ => {
+                    return Err(syn::Error::new(
+                        key.span(),
+                        "unknown test option, expected `timeout`",
+                    ))
+                }
+            }
+            if input.is_empty() {
+                break;
+            }
+            input.parse::<Token![,]>()?;
+        }
+        Ok(attr)
+    }
+}
+
+pub fn expand(attr: ScenarioAttr, item: ItemFn) -> syn::Result<TokenStream> {
+    let sig = &item.sig;
+    if sig.asyncness.is_none() {
+        return Err(syn::Error::new_spanned(
+            sig.fn_token,
+            "scenarios must be `async fn name(session: &mut Session) -> Result<(), E>`",
+        ));
+    }
+    if sig.inputs.len() != 1 {
+        return Err(syn::Error::new_spanned(
+            &sig.inputs,
+            "scenarios take a single `&mut Session` argument",
+        ));
+    }
+    let name = &sig.ident;
+    let runner = format_ident!("__winatep_test_run_{}", name);
+    let timeout = match attr.timeout {
+        Some(lit) => quote!(::core::option::Option::Some(#lit as f32)),
+        None => quote!(::core::option::Option::None),
+    };
+    Ok(quote! {
+        #item
+
+        #[doc(hidden)]
+        fn #runner(
+            session: &mut ::winatep_test::Session,
+        ) -> ::winatep_test::ScenarioFuture<'_> {
+            ::std::boxed::Box::pin(async move {
+                #name(session).await.map_err(::core::convert::Into::into)
+            })
+        }
+
+        ::winatep_test::inventory::submit! {
+            ::winatep_test::Scenario {
+                name: ::core::stringify!(#name),
+                module_path: ::core::module_path!(),
+                timeout_in_seconds: #timeout,
+                run: #runner,
+            }
+        }
+    })
+}
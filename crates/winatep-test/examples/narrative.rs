@@ -0,0 +1,300 @@
+//! An example that runs a test on Narrative Select+Edit
+
+use std::time::{Duration, Instant};
+
+use snafu::prelude::*;
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+use winatep_client::*;
+
+#[derive(Debug, Snafu)]
+enum Error {
+    #[snafu(display("{source}"))]
+    Client { source: winatep_client::Error },
+
+    #[snafu(display("Could not find image"))]
+    CouldNotFindImage,
+}
+
+impl From<winatep_client::Error> for Error {
+    fn from(source: winatep_client::Error) -> Self {
+        Error::Client { source }
+    }
+}
+
+/// Images used for searching and assertions.
+#[derive(Assets)]
+struct NarrativeDemo {
+    /// Tab logo, selected
+    #[asset("../../demo/narrative-logo-tab-selected.png")]
+    logo_tab_selected: ImageBuffer,
+    /// Tab logo, deselected
+    #[asset("../../demo/narrative-logo-tab-deselected.png")]
+    logo_tab_deselected: ImageBuffer,
+    /// Highlighted project sidebar button
+    #[asset("../../demo/narrative-projects-highlighted.png")]
+    projects_button: ImageBuffer,
+    /// Button to create a new project from the projects tab
+    #[asset("../../demo/narrative-new-project-button.png")]
+    new_project_button: ImageBuffer,
+    /// Choose a folder link
+    #[asset("../../demo/narrative-choose-a-folder.png")]
+    choose_a_folder_link: ImageBuffer,
+    /// Finder folder to use as a test project
+    #[asset("../../demo/narrative-test-set-finder-folder.png")]
+    test_set_finder_folder: ImageBuffer,
+    /// Finder folder to use as a test project, when pre-selected
+    #[asset("../../demo/narrative-test-set-finder-folder-selected.png")]
+    test_set_finder_folder_selected: ImageBuffer,
+    /// New project next button
+    #[asset("../../demo/narrative-new-project-next.png")]
+    new_project_next: ImageBuffer,
+    /// Choose your project type
+    #[asset("../../demo/narrative-choose-your-project-type.png")]
+    choose_your_project_type: ImageBuffer,
+}
+
+/// Attempts to find an image within the given screen within a certain timeout.
+///
+/// Returns the most likely position of the center of the image in absolute logical coordinates.
+async fn find_image_in_screen_with_timeout(
+    session: &mut Session,
+    screen: &Screen,
+    imgs: impl IntoIterator<Item = ImageBuffer>,
+) -> Result<Vec2, Error> {
+    let (abs, _screen) = find_any_image_with_timeout(session, [screen.clone()], imgs).await?;
+    Ok(abs)
+}
+
+/// Attempts to find any of the given images within all screens within a certain timeout.
+///
+/// Returns the most likely position of the center of the first image found, in absolute
+/// logical coordinates, along with the screen it was found in.
+async fn find_image_with_timeout(
+    session: &mut Session,
+    imgs: impl IntoIterator<Item = ImageBuffer>,
+) -> Result<(Vec2, Screen), Error> {
+    let screens = session.get_screens().await?;
+    find_any_image_with_timeout(session, screens, imgs).await
+}
+
+/// Attempts to find any of the given images within any of the given screens within a
+/// certain timeout.
+async fn find_any_image_with_timeout(
+    session: &mut Session,
+    screens: impl IntoIterator<Item = Screen>,
+    imgs: impl IntoIterator<Item = ImageBuffer>,
+) -> Result<(Vec2, Screen), Error> {
+    let screens = screens.into_iter().collect::<Vec<_>>();
+    let deadline = Instant::now() + Duration::from_secs_f32(TIMEOUT_SECONDS);
+    let mut matches = session
+        .wait_for_any_image(
+            screens.iter().map(|screen| &screen.name),
+            imgs,
+            FindImageQuality::Standard,
+            FindImageFilter::Standard,
+            TEMPLATE_SCALES,
+            deadline,
+        )
+        .await?;
+    let found = matches.pop().context(CouldNotFindImageSnafu)?;
+    log::debug!(
+        "found image {} in '{}' at scale {}",
+        found.template_index,
+        found.screen_name,
+        found.image_match.scale
+    );
+    let screen = screens
+        .iter()
+        .find(|screen| screen.name == found.screen_name)
+        .context(CouldNotFindImageSnafu)?;
+    let abs = screen.abs_point_from_pixel(found.image_match.location.center());
+    Ok((abs, screen.clone()))
+}
+
+/// Find the image, mouse to it, click on it, return the current mouse position.
+async fn click_on_image_in_screen_with_timeout(
+    session: &mut Session,
+    screen: &Screen,
+    imgs: impl IntoIterator<Item = ImageBuffer>,
+) -> Result<Vec2, Error> {
+    let point = find_image_in_screen_with_timeout(session, screen, imgs).await?;
+    let current_mouse = session.get_mouse_location().await?;
+    session
+        .mouse_motion(
+            [current_mouse, point],
+            Coordinate::Abs,
+            MouseMotion {
+                easing: Easing::MinimumJerk,
+                speed: MotionSpeed::PixelsPerSecond(PIXELS_PER_SECOND),
+                ..Default::default()
+            },
+        )
+        .await?;
+    wait_for_screen_to_settle(session, screen).await?;
+    session.mouse(Button::Left).click(1).await?;
+    wait_for_screen_to_settle(session, screen).await?;
+    Ok(session.get_mouse_location().await?)
+}
+
+/// Wait until the screen stops changing, eg. after hovering or clicking.
+async fn wait_for_screen_to_settle(session: &mut Session, screen: &Screen) -> Result<(), Error> {
+    let deadline = Instant::now() + Duration::from_secs_f32(TIMEOUT_SECONDS);
+    session
+        .wait_for_stable(&screen.name, None, QUIET_PERIOD, deadline)
+        .await?;
+    Ok(())
+}
+
+/// The app under test
+const NARRATIVE_APP: &str = "/Applications/Narrative Select.app";
+
+/// Speed of the cursor
+const PIXELS_PER_SECOND: f32 = 1000.0;
+
+/// Time to wait before giving up on an image search
+const TIMEOUT_SECONDS: f32 = 15.0;
+
+/// Time the screen must be unchanged for before it is considered settled
+const QUIET_PERIOD: Duration = Duration::from_millis(100);
+
+/// The demo templates were cut from Retina screenshots, so they are resized to
+/// match the scale factor of whichever screen is being searched.
+const TEMPLATE_SCALES: FindImageScales = FindImageScales::FromScreen {
+    template_scale_factor: 2.0,
+};
+
+#[winatep_test::test(timeout = 180)]
+async fn narrative(session: &mut Session) -> Result<(), Error> {
+    log::info!("running from directory {:#?}", std::env::current_dir());
+
+    let images = NarrativeDemo::load()?;
+
+    let screens = session.get_screens().await?;
+    log::info!("screens: {screens:#?}");
+    let main_screen = session.get_main_screen().await?;
+    log::info!("main_screen: {main_screen:#?}");
+
+    // Launch Narrative Select and bring its window to the front
+    let process_id = session
+        .launch_app(NARRATIVE_APP, &LaunchOptions::default())
+        .await?;
+    let deadline = Instant::now() + Duration::from_secs_f32(TIMEOUT_SECONDS);
+    let window = session
+        .wait_for_process_window(process_id, deadline)
+        .await?;
+    session.focus_window(&window).await?;
+    log::info!("launched Narrative Select + Edit: {window:#?}");
+
+    // Find the screen that Narrative Select is now running in.
+    //
+    // Narrative could be in one of two states, so we search for two buttons...
+    log::info!("looking for the screen Narrative Select + Edit is running in");
+    let (narrative_logo_tab, narrative_screen) = find_image_with_timeout(
+        session,
+        [
+            // one in the deselected state
+            images.logo_tab_deselected.clone(),
+            // one in selected state
+            images.logo_tab_selected.clone(),
+        ],
+    )
+    .await?;
+    log::info!("...found the screen: {narrative_screen:#?}");
+    let current_mouse = session.get_mouse_location().await?;
+    session
+        .mouse_path([current_mouse, narrative_logo_tab], Coordinate::Abs, 1.0)
+        .await?;
+    session.mouse(Button::Left).click(1).await?;
+
+    // Each step is timed, with screenshots and the matches found drawn on, in
+    // the scenario's steps.html
+    session
+        .step("open projects", async |s| {
+            click_on_image_in_screen_with_timeout(s, &narrative_screen, [images.projects_button])
+                .await
+        })
+        .await?;
+
+    session
+        .step("create a new project", async |s| {
+            click_on_image_in_screen_with_timeout(
+                s,
+                &narrative_screen,
+                [images.new_project_button.clone()],
+            )
+            .await
+        })
+        .await?;
+
+    session
+        .step("choose a folder", async |s| {
+            click_on_image_in_screen_with_timeout(
+                s,
+                &narrative_screen,
+                [images.choose_a_folder_link.clone()],
+            )
+            .await
+        })
+        .await?;
+
+    session
+        .step(
+            "pick the test set in the finder",
+            async |s| -> Result<(), Error> {
+                click_on_image_in_screen_with_timeout(
+                    s,
+                    &narrative_screen,
+                    [
+                        images.test_set_finder_folder.clone(),
+                        images.test_set_finder_folder_selected.clone(),
+                    ],
+                )
+                .await?;
+                s.key_click(Key::Return).await?;
+                Ok(())
+            },
+        )
+        .await?;
+
+    session
+        .step("go to the next page", async |s| {
+            click_on_image_in_screen_with_timeout(
+                s,
+                &narrative_screen,
+                [images.new_project_next.clone()],
+            )
+            .await
+        })
+        .await?;
+
+    session
+        .step("choose the project type", async |s| {
+            click_on_image_in_screen_with_timeout(
+                s,
+                &narrative_screen,
+                [images.choose_your_project_type.clone()],
+            )
+            .await
+        })
+        .await?;
+
+    // Now we should be looking at the project as it is scanning
+    log::info!("done!");
+
+    Ok(())
+}
+
+fn main() -> std::process::ExitCode {
+    // env_logger::builder().init();
+    let perfetto_layer = tracing_perfetto::PerfettoLayer::new(std::sync::Mutex::new(
+        std::fs::File::create("trace.pftrace").unwrap(),
+    ));
+    let filter_layer = tracing_subscriber::EnvFilter::from_default_env();
+    let fmt_layer = tracing_subscriber::fmt::layer();
+    tracing_subscriber::registry()
+        .with(perfetto_layer)
+        .with(filter_layer)
+        .with(fmt_layer)
+        .init();
+    winatep_test::main()
+}
@@ -0,0 +1,73 @@
+//! A test runner harness for WINATEP scenarios.
+//!
+//! Scenarios are async fns that drive a [`Session`], registered with
+//! `#[winatep_test::test]`. Put them in an example or a test target with
+//! `harness = false`, and hand `main` over to the runner:
+//!
+//! ```no_run
+//! use winatep_test::Session;
+//!
+//! #[winatep_test::test(timeout = 60)]
+//! async fn opens_spotlight(session: &mut Session) -> Result<(), winatep_client::Error> {
+//!     session.key_down(winatep_client::Key::Meta).await?;
+//!     session.key_click(winatep_client::Key::Space).await?;
+//!     session.key_up(winatep_client::Key::Meta).await?;
+//!     Ok(())
+//! }
+//!
+//! fn main() -> std::process::ExitCode {
+//!     winatep_test::main()
+//! }
+//! ```
+//!
+//! Each scenario gets its own session, connected to `WINATEP_DRIVER_URL`, and
+//! fails if it returns an error, panics or runs past its timeout. On failure a
+//! screenshot of every screen is saved, and keys and buttons left held down are
+//! always released. Results are written to the report directory as JUnit XML
+//! and JSON, along with an HTML report of each scenario's
+//! [`Session::step`]s.
+use std::{future::Future, pin::Pin};
+
+pub use winatep_client::Session;
+pub use winatep_macros::test;
+
+#[doc(hidden)]
+pub use inventory;
+
+mod report;
+pub use report::{Outcome, Report, ScenarioReport};
+mod runner;
+pub use runner::Runner;
+
+/// The future returned by a scenario.
+pub type ScenarioFuture<'a> =
+    Pin<Box<dyn Future<Output = Result<(), Box<dyn std::error::Error>>> + 'a>>;
+
+/// A scenario registered with `#[winatep_test::test]`.
+pub struct Scenario {
+    pub name: &'static str,
+    pub module_path: &'static str,
+    /// Overrides the runner's timeout.
+    pub timeout_in_seconds: Option<f32>,
+    pub run: for<'a> fn(&'a mut Session) -> ScenarioFuture<'a>,
+}
+
+inventory::collect!(Scenario);
+
+/// Returns every registered scenario, sorted by module path and name.
+pub fn scenarios() -> Vec<&'static Scenario> {
+    let mut scenarios = inventory::iter::<Scenario>.into_iter().collect::<Vec<_>>();
+    scenarios.sort_by_key(|scenario| (scenario.module_path, scenario.name));
+    scenarios
+}
+
+/// Run every registered scenario with the configuration from the environment
+/// and command line, see [`Runner::from_env`].
+pub fn main() -> std::process::ExitCode {
+    let report = Runner::from_env().run();
+    if report.passed() {
+        std::process::ExitCode::SUCCESS
+    } else {
+        std::process::ExitCode::FAILURE
+    }
+}
@@ -0,0 +1,114 @@
+//! Results of a run, as JUnit XML and JSON.
+use std::{fmt::Write as _, path::PathBuf};
+
+use winatep_client::escape_markup;
+
+/// How a scenario ended.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum Outcome {
+    Passed,
+    /// The scenario returned an error.
+    Failed,
+    Panicked,
+    TimedOut,
+}
+
+/// The result of one scenario.
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct ScenarioReport {
+    pub name: String,
+    pub module_path: String,
+    pub outcome: Outcome,
+    pub duration_in_seconds: f64,
+    /// The error, panic or timeout message, if the scenario did not pass.
+    pub message: Option<String>,
+    /// Screenshots of every screen, taken when the scenario did not pass.
+    pub screenshots: Vec<PathBuf>,
+    /// The HTML report of the scenario's steps, if it ran any, see
+    /// [`Session::step`](winatep_client::Session::step).
+    pub step_report: Option<PathBuf>,
+}
+
+/// The results of a run.
+#[derive(Clone, Debug, Default, serde::Serialize, serde::Deserialize)]
+pub struct Report {
+    pub scenarios: Vec<ScenarioReport>,
+    pub duration_in_seconds: f64,
+}
+
+impl Report {
+    pub fn passed(&self) -> bool {
+        self.failures() == 0
+    }
+
+    pub fn failures(&self) -> usize {
+        self.scenarios
+            .iter()
+            .filter(|scenario| scenario.outcome != Outcome::Passed)
+            .count()
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("reports are always serializable")
+    }
+
+    /// Returns the report in the JUnit XML format understood by most CI
+    /// systems.
+    pub fn to_junit_xml(&self) -> String {
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        let tests = self.scenarios.len();
+        let failures = self.failures();
+        let time = self.duration_in_seconds;
+        let _ = writeln!(
+            xml,
+            "<testsuites tests=\"{tests}\" failures=\"{failures}\" time=\"{time:.3}\">"
+        );
+        let _ = writeln!(
+            xml,
+            "  <testsuite name=\"winatep\" tests=\"{tests}\" failures=\"{failures}\" time=\"{time:.3}\">"
+        );
+        for scenario in &self.scenarios {
+            let _ = write!(
+                xml,
+                "    <testcase name=\"{}\" classname=\"{}\" time=\"{:.3}\"",
+                escape_markup(&scenario.name),
+                escape_markup(&scenario.module_path),
+                scenario.duration_in_seconds
+            );
+            if scenario.outcome == Outcome::Passed {
+                xml.push_str("/>\n");
+                continue;
+            }
+            xml.push_str(">\n");
+            let message = scenario.message.as_deref().unwrap_or_default();
+            let _ = writeln!(
+                xml,
+                "      <failure type=\"{:?}\" message=\"{}\">{}</failure>",
+                scenario.outcome,
+                escape_markup(message.lines().next().unwrap_or_default()),
+                escape_markup(message)
+            );
+            if !scenario.screenshots.is_empty() || scenario.step_report.is_some() {
+                xml.push_str("      <system-out>");
+                for screenshot in &scenario.screenshots {
+                    let _ = writeln!(
+                        xml,
+                        "screenshot: {}",
+                        escape_markup(&screenshot.to_string_lossy())
+                    );
+                }
+                if let Some(step_report) = &scenario.step_report {
+                    let _ = writeln!(
+                        xml,
+                        "steps: {}",
+                        escape_markup(&step_report.to_string_lossy())
+                    );
+                }
+                xml.push_str("</system-out>\n");
+            }
+            xml.push_str("    </testcase>\n");
+        }
+        xml.push_str("  </testsuite>\n</testsuites>\n");
+        xml
+    }
+}
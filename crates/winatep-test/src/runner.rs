@@ -0,0 +1,279 @@
+//! Running scenarios one after another.
+use std::{
+    panic::AssertUnwindSafe,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use futures_util::FutureExt;
+use winatep_client::{Session, Token};
+
+use crate::{Outcome, Report, Scenario, ScenarioReport};
+
+/// The environment variable with the URL of the driver.
+const DRIVER_URL_VAR: &str = "WINATEP_DRIVER_URL";
+/// The environment variable with the default timeout of each scenario, in
+/// seconds.
+const TIMEOUT_VAR: &str = "WINATEP_TIMEOUT";
+/// The environment variable with the directory reports and screenshots are
+/// written to.
+const REPORT_DIR_VAR: &str = "WINATEP_REPORT_DIR";
+
+const DEFAULT_DRIVER_URL: &str = "ws://127.0.0.1:31337/driver";
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Runs registered scenarios and reports their results.
+#[derive(Clone, Debug)]
+pub struct Runner {
+    driver_url: String,
+    timeout: Duration,
+    report_dir: PathBuf,
+    filter: Option<String>,
+}
+
+impl Default for Runner {
+    fn default() -> Self {
+        Runner {
+            driver_url: DEFAULT_DRIVER_URL.to_owned(),
+            timeout: DEFAULT_TIMEOUT,
+            report_dir: "target/winatep".into(),
+            filter: None,
+        }
+    }
+}
+
+impl Runner {
+    /// Configure the runner from the environment and command line:
+    ///
+    /// * `WINATEP_DRIVER_URL`, the driver to connect to, defaults to
+    ///   `ws://127.0.0.1:31337/driver`.
+    /// * `WINATEP_TIMEOUT`, the timeout of each scenario in seconds, defaults to
+    ///   300.
+    /// * `WINATEP_REPORT_DIR`, where reports and screenshots are written,
+    ///   defaults to `target/winatep`.
+    /// * The first command line argument, if any, only runs scenarios whose
+    ///   path contains it.
+    pub fn from_env() -> Self {
+        let mut runner = Runner::default();
+        if let Ok(url) = std::env::var(DRIVER_URL_VAR) {
+            runner.driver_url = url;
+        }
+        match std::env::var(TIMEOUT_VAR).map(|t| t.parse::<f32>()) {
+            Ok(Ok(seconds)) => match timeout_from_secs(seconds) {
+                Some(timeout) => runner.timeout = timeout,
+                None => log::warn!("ignoring {TIMEOUT_VAR}: {seconds} is not a positive timeout"),
+            },
+            Ok(Err(e)) => log::warn!("ignoring {TIMEOUT_VAR}: {e}"),
+            Err(_) => {}
+        }
+        if let Some(dir) = std::env::var_os(REPORT_DIR_VAR) {
+            runner.report_dir = dir.into();
+        }
+        runner.filter = std::env::args().skip(1).find(|arg| !arg.starts_with('-'));
+        runner
+    }
+
+    pub fn driver_url(mut self, url: impl Into<String>) -> Self {
+        self.driver_url = url.into();
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn report_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.report_dir = dir.into();
+        self
+    }
+
+    pub fn filter(mut self, filter: Option<String>) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    /// Run every registered scenario matching the filter, print their results
+    /// and write the reports.
+    ///
+    /// Logging is set up from `RUST_LOG`, unless a subscriber is already set.
+    pub fn run(&self) -> Report {
+        let _ = tracing_subscriber::fmt()
+            .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+            .try_init();
+        let scenarios = crate::scenarios()
+            .into_iter()
+            .filter(|scenario| match &self.filter {
+                Some(filter) => {
+                    format!("{}::{}", scenario.module_path, scenario.name).contains(filter)
+                }
+                None => true,
+            })
+            .collect::<Vec<_>>();
+        println!("\nrunning {} scenarios", scenarios.len());
+
+        let started = Instant::now();
+        let mut report = Report::default();
+        for scenario in scenarios {
+            let result = async_std::task::block_on(self.run_scenario(scenario));
+            match result.outcome {
+                Outcome::Passed => println!("scenario {} ... ok", result.name),
+                outcome => println!("scenario {} ... {outcome:?}", result.name),
+            }
+            report.scenarios.push(result);
+        }
+        report.duration_in_seconds = started.elapsed().as_secs_f64();
+
+        for failed in report
+            .scenarios
+            .iter()
+            .filter(|s| s.outcome != Outcome::Passed)
+        {
+            println!("\n---- {} ----", failed.name);
+            println!("{}", failed.message.as_deref().unwrap_or_default());
+            for screenshot in &failed.screenshots {
+                println!("screenshot: {}", screenshot.display());
+            }
+            if let Some(step_report) = &failed.step_report {
+                println!("steps: {}", step_report.display());
+            }
+        }
+        let failures = report.failures();
+        println!(
+            "\nscenario result: {}. {} passed; {failures} failed; finished in {:.2}s\n",
+            if failures == 0 { "ok" } else { "FAILED" },
+            report.scenarios.len() - failures,
+            report.duration_in_seconds
+        );
+        self.write_reports(&report);
+        report
+    }
+
+    fn write_reports(&self, report: &Report) {
+        let written = std::fs::create_dir_all(&self.report_dir)
+            .and_then(|()| std::fs::write(self.report_dir.join("junit.xml"), report.to_junit_xml()))
+            .and_then(|()| std::fs::write(self.report_dir.join("report.json"), report.to_json()));
+        if let Err(e) = written {
+            log::error!(
+                "could not write reports to '{}': {e}",
+                self.report_dir.display()
+            );
+        }
+    }
+
+    async fn run_scenario(&self, scenario: &Scenario) -> ScenarioReport {
+        let timeout = match scenario.timeout_in_seconds {
+            Some(seconds) => timeout_from_secs(seconds).unwrap_or_else(|| {
+                log::warn!(
+                    "ignoring the timeout of '{}': {seconds} is not a positive timeout",
+                    scenario.name
+                );
+                self.timeout
+            }),
+            None => self.timeout,
+        };
+        // scenarios in different modules may share a name
+        let scenario_dir = scenario
+            .module_path
+            .split("::")
+            .fold(self.report_dir.clone(), |dir, module| dir.join(module))
+            .join(scenario.name);
+        let started = Instant::now();
+        let mut report = ScenarioReport {
+            name: scenario.name.to_owned(),
+            module_path: scenario.module_path.to_owned(),
+            outcome: Outcome::Passed,
+            duration_in_seconds: 0.0,
+            message: None,
+            screenshots: vec![],
+            step_report: None,
+        };
+
+        let mut session = match self.connect(&scenario_dir).await {
+            Ok(session) => session,
+            Err(e) => {
+                report.outcome = Outcome::Failed;
+                report.message = Some(format!("could not connect to the driver: {e}"));
+                return report;
+            }
+        };
+        let run = AssertUnwindSafe((scenario.run)(&mut session)).catch_unwind();
+        let (outcome, message) = match async_std::future::timeout(timeout, run).await {
+            Ok(Ok(Ok(()))) => (Outcome::Passed, None),
+            Ok(Ok(Err(e))) => (Outcome::Failed, Some(e.to_string())),
+            Ok(Err(panic)) => (Outcome::Panicked, Some(panic_message(&*panic))),
+            Err(_) => (
+                Outcome::TimedOut,
+                Some(format!("timed out after {:.1}s", timeout.as_secs_f32())),
+            ),
+        };
+        report.duration_in_seconds = started.elapsed().as_secs_f64();
+        report.outcome = outcome;
+        report.message = message;
+        if !session.step_records().is_empty() {
+            match session.write_step_report(scenario_dir.join("steps.html")) {
+                Ok(path) => report.step_report = Some(path),
+                Err(e) => log::error!("could not write the steps of '{}': {e}", scenario.name),
+            }
+        }
+
+        // A scenario that timed out or panicked may have left a reply unread,
+        // so clean up over a fresh connection.
+        let mut orphaned = vec![];
+        if matches!(outcome, Outcome::TimedOut | Outcome::Panicked) {
+            orphaned = session.held_inputs().to_vec();
+            session = match self.connect(&scenario_dir).await {
+                Ok(session) => session,
+                Err(e) => {
+                    log::error!(
+                        "could not reconnect to clean up after '{}': {e}",
+                        scenario.name
+                    );
+                    return report;
+                }
+            };
+        }
+        if outcome != Outcome::Passed {
+            report.screenshots = session.save_screenshots(&scenario_dir, "failure").await;
+        }
+        for release in orphaned.iter().rev().filter_map(Token::released) {
+            if let Err(e) = session.input(release).await {
+                log::error!(
+                    "could not release held input after '{}': {e}",
+                    scenario.name
+                );
+            }
+        }
+        if let Err(e) = session.release_held_inputs().await {
+            log::error!(
+                "could not release held inputs after '{}': {e}",
+                scenario.name
+            );
+        }
+        report
+    }
+
+    async fn connect(&self, scenario_dir: &Path) -> Result<Session, winatep_client::Error> {
+        let mut session = Session::new(&self.driver_url).await?;
+        if std::env::var_os("WINATEP_DEBUG_DIR").is_none() {
+            session.set_debug_dir(Some(scenario_dir.to_path_buf()));
+        }
+        Ok(session)
+    }
+}
+
+/// Returns `None` unless the seconds are positive and fit a `Duration`.
+fn timeout_from_secs(seconds: f32) -> Option<Duration> {
+    Duration::try_from_secs_f32(seconds)
+        .ok()
+        .filter(|timeout| !timeout.is_zero())
+}
+
+fn panic_message(panic: &(dyn std::any::Any + Send)) -> String {
+    let message = panic
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| panic.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("panicked");
+    format!("panicked: {message}")
+}
@@ -0,0 +1,115 @@
+//! Elements of the accessibility tree, and queries for them.
+use crate::{BoundingRectangle, TextQuery};
+
+/// A state an accessibility element can be in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub enum ElementState {
+    Focused,
+    Selected,
+    Checked,
+    Expanded,
+    Disabled,
+    Editable,
+    Offscreen,
+}
+
+/// An element of an application's user interface, as exposed to assistive
+/// technologies.
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct AccessibilityElement {
+    /// The role of the element, normalized across OSes, eg. `"button"`,
+    /// `"text field"` or `"window"`.
+    pub role: String,
+    /// The label of the element, eg. the title of a button.
+    #[serde(default)]
+    pub name: Option<String>,
+    /// The value of the element, eg. the contents of a text field.
+    #[serde(default)]
+    pub value: Option<String>,
+    #[serde(default)]
+    pub states: Vec<ElementState>,
+    /// The bounds of the element in absolute logical coordinates.
+    pub bounds: BoundingRectangle,
+    /// Child elements. Empty for elements returned by `FindElement`.
+    #[serde(default)]
+    pub children: Vec<AccessibilityElement>,
+}
+
+impl AccessibilityElement {
+    pub fn has_state(&self, state: ElementState) -> bool {
+        self.states.contains(&state)
+    }
+
+    /// Returns this element and all of its descendants, depth first.
+    pub fn descendants(&self) -> Vec<&AccessibilityElement> {
+        let mut elements = vec![];
+        let mut stack = vec![self];
+        while let Some(element) = stack.pop() {
+            elements.push(element);
+            stack.extend(element.children.iter().rev());
+        }
+        elements
+    }
+}
+
+/// A query for accessibility elements. Unset fields match any element.
+///
+/// ```
+/// use winatep_wire_types::{ElementQuery, ElementState};
+///
+/// let query = ElementQuery::role("button")
+///     .name("Export")
+///     .with_state(ElementState::Focused);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ElementQuery {
+    /// The role of the element, compared case-insensitively.
+    #[serde(default)]
+    pub role: Option<String>,
+    #[serde(default)]
+    pub name: Option<TextQuery>,
+    #[serde(default)]
+    pub value: Option<TextQuery>,
+    /// States the element must all be in.
+    #[serde(default)]
+    pub states: Vec<ElementState>,
+    /// Only search within this window, see `Window::id`.
+    #[serde(default)]
+    pub window_id: Option<u64>,
+}
+
+impl ElementQuery {
+    pub fn role(role: impl Into<String>) -> Self {
+        ElementQuery {
+            role: Some(role.into()),
+            ..Default::default()
+        }
+    }
+
+    pub fn named(name: impl Into<TextQuery>) -> Self {
+        ElementQuery {
+            name: Some(name.into()),
+            ..Default::default()
+        }
+    }
+
+    pub fn name(mut self, name: impl Into<TextQuery>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    pub fn value(mut self, value: impl Into<TextQuery>) -> Self {
+        self.value = Some(value.into());
+        self
+    }
+
+    pub fn with_state(mut self, state: ElementState) -> Self {
+        self.states.push(state);
+        self
+    }
+
+    pub fn in_window(mut self, window_id: u64) -> Self {
+        self.window_id = Some(window_id);
+        self
+    }
+}
@@ -0,0 +1,45 @@
+//! Clipboard contents beyond plain text.
+use crate::{ImageBuffer, Secret};
+
+/// A format the clipboard can hold.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub enum ClipboardFormat {
+    Text,
+    Image,
+    Html,
+    Rtf,
+    Files,
+}
+
+/// The clipboard contents in one format.
+///
+/// The clipboard can hold the same contents in several formats at once, eg.
+/// HTML with a plain text alternative.
+///
+/// Text is redacted from logs, since the clipboard often holds passwords.
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub enum ClipboardContent {
+    Text(Secret<String>),
+    Image(ImageBuffer),
+    Html {
+        html: Secret<String>,
+        /// Plain text for apps that do not accept HTML.
+        #[serde(default)]
+        alt_text: Option<Secret<String>>,
+    },
+    Rtf(Secret<String>),
+    /// Absolute paths of files, eg. as copied in Finder or Explorer.
+    Files(Vec<String>),
+}
+
+impl ClipboardContent {
+    pub fn format(&self) -> ClipboardFormat {
+        match self {
+            ClipboardContent::Text(_) => ClipboardFormat::Text,
+            ClipboardContent::Image(_) => ClipboardFormat::Image,
+            ClipboardContent::Html { .. } => ClipboardFormat::Html,
+            ClipboardContent::Rtf(_) => ClipboardFormat::Rtf,
+            ClipboardContent::Files(_) => ClipboardFormat::Files,
+        }
+    }
+}
@@ -0,0 +1,78 @@
+//! Events pushed by the driver without being requested.
+use crate::{BoundingRectangle, ImageBuffer, Screen, Token, Vec2, Window};
+
+/// A kind of event to subscribe to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub enum EventKind {
+    /// Screens added, removed, moved or rescaled.
+    Screens,
+    /// The foreground window changing.
+    Focus,
+    Clipboard,
+    /// Real keyboard and mouse input, ie. not sent by a client, which may
+    /// interfere with a run.
+    UserInput,
+    /// Input recorded between `InputMessage::StartRecording` and the end of
+    /// the recording. Pushed without subscribing.
+    Recording,
+}
+
+/// An event pushed by the driver.
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub enum DriverEvent {
+    ScreenAdded(Screen),
+    ScreenRemoved {
+        name: String,
+    },
+    /// A screen was moved, resized or rescaled.
+    ScreenChanged(Screen),
+    /// `None` if no window has focus, eg. when the desktop is focused.
+    FocusChanged {
+        window: Option<Window>,
+    },
+    ClipboardChanged {
+        change_count: u64,
+    },
+    UserInput(Token),
+    /// Real input recorded after `InputMessage::StartRecording`. Mouse
+    /// locations are absolute.
+    Recorded(Token),
+    /// The screen around a recorded button press, captured as it happened.
+    /// Pushed just before the press's `Recorded` event if `anchor_size` was
+    /// set.
+    RecordedAnchor {
+        screen_name: String,
+        /// The captured region, in pixels relative to the screen.
+        region: BoundingRectangle,
+        /// Where the press happened, in pixels relative to the screen.
+        location: Vec2,
+        image_buffer: ImageBuffer,
+    },
+    /// The recording ended, because of `InputMessage::StopRecording`, the stop
+    /// key or the timeout. Always the last recording event.
+    RecordingStopped,
+}
+
+impl DriverEvent {
+    pub fn kind(&self) -> EventKind {
+        match self {
+            DriverEvent::ScreenAdded(_)
+            | DriverEvent::ScreenRemoved { .. }
+            | DriverEvent::ScreenChanged(_) => EventKind::Screens,
+            DriverEvent::FocusChanged { .. } => EventKind::Focus,
+            DriverEvent::ClipboardChanged { .. } => EventKind::Clipboard,
+            DriverEvent::UserInput(_) => EventKind::UserInput,
+            DriverEvent::Recorded(_)
+            | DriverEvent::RecordedAnchor { .. }
+            | DriverEvent::RecordingStopped => EventKind::Recording,
+        }
+    }
+}
+
+/// A [`DriverEvent`] with the time it happened.
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct TimestampedEvent {
+    /// Seconds since the client connected, as measured by the driver.
+    pub timestamp_in_seconds: f64,
+    pub event: DriverEvent,
+}
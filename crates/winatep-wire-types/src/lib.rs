@@ -3,8 +3,24 @@
 use glam::UVec2;
 pub use glam::Vec2;
 
+mod accessibility;
+pub use accessibility::*;
+mod clipboard;
+pub use clipboard::*;
+mod event;
+pub use event::*;
 mod key;
 pub use key::*;
+mod motion;
+pub use motion::*;
+mod process;
+pub use process::*;
+mod secret;
+pub use secret::*;
+mod text;
+pub use text::*;
+mod window;
+pub use window::*;
 
 /// Information about a screen or monitor.
 #[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
@@ -32,6 +48,18 @@ impl Screen {
     pub fn contains_abs_point(&self, point: glam::Vec2) -> bool {
         self.bounds().contains_point(point)
     }
+
+    /// Converts a point in pixels relative to the screen, eg. the center of an
+    /// image match, to absolute logical coordinates.
+    pub fn abs_point_from_pixel(&self, pixel: glam::Vec2) -> glam::Vec2 {
+        self.bounds().min + pixel / self.scale_factor
+    }
+
+    /// Converts a point in absolute logical coordinates to pixels relative to
+    /// the screen.
+    pub fn pixel_from_abs_point(&self, point: glam::Vec2) -> glam::Vec2 {
+        (point - self.bounds().min) * self.scale_factor
+    }
 }
 
 /// A server status message.
@@ -81,15 +109,29 @@ impl core::fmt::Debug for ImageBuffer {
 
 impl From<image::DynamicImage> for ImageBuffer {
     fn from(img: image::DynamicImage) -> Self {
-        let rgb_img = img.into_rgb8();
+        img.into_rgb8().into()
+    }
+}
+
+impl From<image::RgbImage> for ImageBuffer {
+    fn from(rgb_img: image::RgbImage) -> Self {
         Self {
             width: rgb_img.width(),
             height: rgb_img.height(),
-            buffer: rgb_img.to_vec(),
+            buffer: rgb_img.into_raw(),
         }
     }
 }
 
+impl ImageBuffer {
+    /// Copies the buffer into an [`image::RgbImage`].
+    ///
+    /// Returns `None` if the buffer is not `width * height` RGB8 pixels.
+    pub fn to_rgb_image(&self) -> Option<image::RgbImage> {
+        image::RgbImage::from_raw(self.width, self.height, self.buffer.clone())
+    }
+}
+
 /// The direction of a key or button.
 #[derive(serde::Serialize, serde::Deserialize, Debug, Default, Clone, PartialEq, Eq, Hash)]
 pub enum Direction {
@@ -128,13 +170,14 @@ pub enum Button {
     #[serde(alias = "r")]
     Right,
     /// 4th mouse button. Typically performs the same function as `Browser_Back`
-    #[cfg(any(target_os = "windows", all(unix, not(target_os = "macos"))))]
+    ///
+    /// Drivers on macOS cannot send the 4th and 5th buttons, and reply with an
+    /// error.
     #[serde(alias = "B")]
     #[serde(alias = "b")]
     Back,
     /// 5th mouse button. Typically performs the same function as
     /// `Browser_Forward`
-    #[cfg(any(target_os = "windows", all(unix, not(target_os = "macos"))))]
     #[serde(alias = "F")]
     #[serde(alias = "f")]
     Forward,
@@ -177,6 +220,18 @@ pub enum Coordinate {
     Rel,
 }
 
+/// Specifies the unit of a scroll amount
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Hash, Default, serde::Serialize, serde::Deserialize,
+)]
+pub enum ScrollUnit {
+    /// Lines, or wheel notches, as with [`Token::Scroll`]
+    #[default]
+    Line,
+    /// Pixels, as with a trackpad
+    Pixel,
+}
+
 /// Specifies the axis for scrolling
 #[derive(
     Debug, Clone, Copy, PartialEq, Eq, Hash, Default, serde::Serialize, serde::Deserialize,
@@ -234,6 +289,21 @@ pub enum Token {
     MainDisplay(i32, i32),
 }
 
+impl Token {
+    /// Returns the token that releases the key or button this token presses,
+    /// or `None` if it does not press one.
+    pub fn released(&self) -> Option<Token> {
+        match self {
+            Token::Key(key, Direction::Press) => Some(Token::Key(*key, Direction::Release)),
+            Token::Raw(code, Direction::Press) => Some(Token::Raw(*code, Direction::Release)),
+            Token::Button(button, Direction::Press) => {
+                Some(Token::Button(button.clone(), Direction::Release))
+            }
+            _ => None,
+        }
+    }
+}
+
 /// An axis-aligned bounding rectangle.
 #[derive(Clone, Copy, Debug, Default, serde::Serialize, serde::Deserialize)]
 pub struct BoundingRectangle {
@@ -312,6 +382,83 @@ impl FindImageFilter {
     }
 }
 
+/// Sets the scales at which a template image is searched for within a screen.
+///
+/// A template cut from a screenshot of a screen with one scale factor will not
+/// match the same UI on a screen with a different scale factor unless it is
+/// resized first.
+#[derive(Clone, Debug, Default, serde::Serialize, serde::Deserialize)]
+pub enum FindImageScales {
+    /// Search for the template at its original size only.
+    #[default]
+    Original,
+    /// Derive the scale from the target screen's [`Screen::scale_factor`].
+    ///
+    /// - `template_scale_factor`:
+    ///   The scale factor of the screen the template was captured on, eg. `2.0`
+    ///   for a template cut from a Retina screenshot.
+    FromScreen { template_scale_factor: f32 },
+    /// Search for the template at each of the given scales.
+    Specific(Vec<f32>),
+}
+
+impl FindImageScales {
+    /// Returns the scales the template should be resized by before searching
+    /// within the given screen.
+    pub fn scales_for_screen(&self, screen: &Screen) -> Vec<f32> {
+        match self {
+            FindImageScales::Original => vec![1.0],
+            FindImageScales::FromScreen {
+                template_scale_factor,
+            } => vec![screen.scale_factor / template_scale_factor],
+            FindImageScales::Specific(scales) => scales.clone(),
+        }
+    }
+}
+
+/// A sub-image found within a screen.
+#[derive(Clone, Copy, Debug, serde::Serialize, serde::Deserialize)]
+pub struct ImageMatch {
+    /// Location of the match, in pixels relative to the screen.
+    pub location: BoundingRectangle,
+    /// The scale the template was resized by to produce this match.
+    pub scale: f32,
+    /// Visual similarity of the match, between 0.0 and 1.0.
+    pub similarity: f32,
+}
+
+/// One of many templates found within one of many screens.
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct TemplateMatch {
+    /// Name of the screen the template was found in.
+    pub screen_name: String,
+    /// Index of the template that matched, in the order the templates were given.
+    pub template_index: usize,
+    /// The match itself.
+    pub image_match: ImageMatch,
+}
+
+/// A word recognized within a screen.
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct RecognizedWord {
+    pub text: String,
+    /// Location of the word, in pixels relative to the screen.
+    pub location: BoundingRectangle,
+    /// Confidence of the recognition, between 0.0 and 1.0.
+    pub confidence: f32,
+}
+
+/// A line of text recognized within a screen.
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct RecognizedLine {
+    pub text: String,
+    /// Location of the line, in pixels relative to the screen.
+    pub location: BoundingRectangle,
+    /// Confidence of the recognition, between 0.0 and 1.0.
+    pub confidence: f32,
+    pub words: Vec<RecognizedWord>,
+}
+
 /// Websocket driver input messages.
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub enum InputMessage {
@@ -321,21 +468,164 @@ pub enum InputMessage {
         name: String,
     },
     GetMouseLocation,
+    /// Get the longest time between two clicks for them to count as a
+    /// double-click.
+    GetDoubleClickInterval,
     DoInput(Token),
-    DoTypeText(String),
+    /// Type the text at once. The text is redacted from logs, since it is
+    /// often a password.
+    DoTypeText(Secret<String>),
+    /// Scroll at the current location of the mouse. Positive `delta.x` scrolls
+    /// right, positive `delta.y` scrolls down.
+    ///
+    /// `smooth` animates the scroll over a short time, like a trackpad.
+    DoScroll {
+        delta: Vec2,
+        unit: ScrollUnit,
+        smooth: bool,
+    },
+    /// Move the mouse along the waypoints, timed by the driver.
+    DoMouseMotion {
+        path: Vec<Vec2>,
+        coordinate: Coordinate,
+        motion: MouseMotion,
+    },
     FindText {
-        text: String,
+        /// Sent as `text`, a plain string for literal queries, as drivers that
+        /// predate queries expect.
+        #[serde(rename = "text", alias = "query")]
+        query: TextQuery,
         screen_name: String,
         timeout_in_seconds: f32,
     },
+    /// Recognize all the text within a region of a screen.
+    ///
+    /// The region is in pixels relative to the screen. `None` reads the whole
+    /// screen.
+    ReadText {
+        screen_name: String,
+        region: Option<BoundingRectangle>,
+    },
     FindImage {
         screen_name: String,
         image: ImageBuffer,
         quality: FindImageQuality,
         filter: FindImageFilter,
+        #[serde(default)]
+        scales: FindImageScales,
+        /// Only search within this region, in pixels relative to the screen.
+        /// `None` searches the whole screen.
+        #[serde(default)]
+        region: Option<BoundingRectangle>,
+    },
+    /// Search each of the screens for each of the templates.
+    ///
+    /// An empty list of screens searches every screen.
+    FindAnyImage {
+        screens: Vec<String>,
+        templates: Vec<ImageBuffer>,
+        quality: FindImageQuality,
+        filter: FindImageFilter,
+        #[serde(default)]
+        scales: FindImageScales,
     },
     GetClipboardText,
-    SetClipboardText(String),
+    /// The text is redacted from logs, since it is often a password.
+    SetClipboardText(Secret<String>),
+    /// List the formats currently on the clipboard.
+    GetClipboardFormats,
+    /// Get the clipboard contents in one format.
+    GetClipboard(ClipboardFormat),
+    /// Replace the clipboard contents with the contents in every given format.
+    /// An empty list clears the clipboard.
+    SetClipboard(Vec<ClipboardContent>),
+    /// Get a number that increases whenever the clipboard contents change.
+    GetClipboardChangeCount,
+    /// List the open windows of every application, frontmost first.
+    ListWindows,
+    /// Bring the window to the front and give it keyboard focus, restoring it
+    /// if minimized.
+    FocusWindow {
+        window_id: u64,
+    },
+    /// Move the top-left corner of the window, in absolute logical coordinates.
+    MoveWindow {
+        window_id: u64,
+        location: Vec2,
+    },
+    /// Resize the window, in logical coordinates.
+    ResizeWindow {
+        window_id: u64,
+        size: Vec2,
+    },
+    MinimizeWindow {
+        window_id: u64,
+    },
+    /// Ask the window to close, as if its close button was clicked.
+    CloseWindow {
+        window_id: u64,
+    },
+    /// Launch an application by the path of its executable or app bundle, or
+    /// by its bundle ID on macOS, eg. `"com.apple.TextEdit"`.
+    LaunchApp {
+        path_or_bundle_id: String,
+        #[serde(default)]
+        args: Vec<String>,
+        /// Variables added to the environment of the driver. The values are
+        /// redacted from logs, since they often hold tokens.
+        #[serde(default)]
+        env: std::collections::BTreeMap<String, Secret<String>>,
+    },
+    ListProcesses,
+    /// Ask the process to quit, or kill it if `force` is set.
+    TerminateProcess {
+        process_id: u32,
+        force: bool,
+    },
+
+    /// Get the accessibility tree of a window, see `Window::id`.
+    GetAccessibilityTree {
+        window_id: u64,
+    },
+    /// Find accessibility elements matching the query, frontmost window first.
+    FindElement {
+        query: ElementQuery,
+    },
+
+    /// Start pushing events of the given kinds, as `OutputMessage::Event`s.
+    Subscribe(Vec<EventKind>),
+    /// Stop pushing events of the given kinds.
+    Unsubscribe(Vec<EventKind>),
+    /// Start pushing real keyboard and mouse input as `DriverEvent::Recorded`
+    /// events, until `StopRecording`, the stop key or the timeout.
+    StartRecording {
+        /// A key that ends the recording when pressed. It is not recorded.
+        stop_key: Option<Key>,
+        timeout_in_seconds: Option<f32>,
+        /// Also record every mouse move, not only those before a button or
+        /// scroll event.
+        #[serde(default)]
+        all_mouse_moves: bool,
+        /// Capture a region this many pixels square around each button press,
+        /// sent as a `DriverEvent::RecordedAnchor`.
+        #[serde(default)]
+        anchor_size: Option<u32>,
+    },
+    StopRecording,
+    /// Capture the screen repeatedly until the region has not changed for the
+    /// quiet period.
+    ///
+    /// The region is in pixels relative to the screen. `None` waits for the
+    /// whole screen.
+    WaitForStable {
+        screen_name: String,
+        region: Option<BoundingRectangle>,
+        quiet_period_in_seconds: f32,
+        timeout_in_seconds: f32,
+        /// Send the stable region back in `OutputMessage::ScreenStable`.
+        #[serde(default)]
+        return_capture: bool,
+    },
 }
 
 /// Websocket driver output messages.
@@ -343,13 +633,120 @@ pub enum InputMessage {
 pub enum OutputMessage {
     GotScreens(Vec<Screen>),
     GotMainScreen(Screen),
-    CapturedScreen { image_buffer: ImageBuffer },
+    CapturedScreen {
+        image_buffer: ImageBuffer,
+    },
     GotMouseLocation(Vec2),
+    GotDoubleClickInterval {
+        seconds: f32,
+    },
     DidInput,
     DidTypeText,
-    FoundText { locations: Vec<BoundingRectangle> },
-    FoundImage { locations: Vec<BoundingRectangle> },
-    GotClipboardText(String),
+    DidMouseMotion,
+    DidScroll,
+    FoundText {
+        locations: Vec<BoundingRectangle>,
+        /// The same matches as `locations`, with the text that matched.
+        /// Drivers that predate queries only send `locations`.
+        #[serde(default)]
+        matches: Vec<TextMatch>,
+    },
+    RecognizedText {
+        lines: Vec<RecognizedLine>,
+    },
+    FoundImage {
+        locations: Vec<BoundingRectangle>,
+        /// The same matches as `locations`, with their scale and similarity.
+        /// Drivers that predate scales only send `locations`.
+        #[serde(default)]
+        matches: Vec<ImageMatch>,
+    },
+    FoundAnyImage {
+        matches: Vec<TemplateMatch>,
+    },
+    /// The text is redacted from logs, since it is often a password.
+    GotClipboardText(Secret<String>),
     DidSetClipboardText,
+    GotClipboardFormats(Vec<ClipboardFormat>),
+    /// `None` if the clipboard does not hold the requested format.
+    GotClipboard(Option<ClipboardContent>),
+    DidSetClipboard,
+    GotClipboardChangeCount(u64),
+    GotWindows(Vec<Window>),
+    DidFocusWindow,
+    DidMoveWindow,
+    DidResizeWindow,
+    DidMinimizeWindow,
+    DidCloseWindow,
+    LaunchedApp {
+        process_id: u32,
+    },
+    GotProcesses(Vec<Process>),
+    DidTerminateProcess,
+
+    GotAccessibilityTree(Box<AccessibilityElement>),
+    FoundElements(Vec<AccessibilityElement>),
+
+    DidSubscribe,
+    DidUnsubscribe,
+    DidStartRecording,
+    DidStopRecording,
+    /// Pushed by the driver at any time after `InputMessage::Subscribe`,
+    /// including between a request and its reply.
+    Event(TimestampedEvent),
+    /// The region stopped changing. Holds its capture only if
+    /// `return_capture` was set.
+    ScreenStable {
+        image_buffer: Option<ImageBuffer>,
+    },
     Error(String),
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn found_image_from_older_drivers_decodes() {
+        let json = r#"{"FoundImage":{"locations":[{"min":[1.0,2.0],"max":[3.0,4.0]}]}}"#;
+        let msg: OutputMessage = serde_json::from_str(json).unwrap();
+        let OutputMessage::FoundImage { locations, matches } = msg else {
+            panic!("unexpected {msg:?}");
+        };
+        assert_eq!(locations.len(), 1);
+        assert_eq!(locations[0].max, Vec2::new(3.0, 4.0));
+        assert!(matches.is_empty());
+    }
+
+    #[test]
+    fn find_text_from_older_clients_decodes() {
+        let json = r#"{"FindText":{"text":"Next","screen_name":"main","timeout_in_seconds":1.0}}"#;
+        let msg: InputMessage = serde_json::from_str(json).unwrap();
+        let InputMessage::FindText { query, .. } = msg else {
+            panic!("unexpected {msg:?}");
+        };
+        assert_eq!(query, TextQuery::literal("Next"));
+    }
+
+    #[test]
+    fn literal_find_text_is_sent_in_the_older_shape() {
+        let msg = InputMessage::FindText {
+            query: "Next".into(),
+            screen_name: "main".to_owned(),
+            timeout_in_seconds: 1.0,
+        };
+        let json = serde_json::to_value(&msg).unwrap();
+        assert_eq!(json["FindText"]["text"], "Next");
+    }
+
+    #[test]
+    fn found_text_from_older_drivers_decodes() {
+        let json = r#"{"FoundText":{"locations":[{"min":[1.0,2.0],"max":[3.0,4.0]}]}}"#;
+        let msg: OutputMessage = serde_json::from_str(json).unwrap();
+        let OutputMessage::FoundText { locations, matches } = msg else {
+            panic!("unexpected {msg:?}");
+        };
+        assert_eq!(locations.len(), 1);
+        assert!(matches.is_empty());
+    }
+}
@@ -0,0 +1,395 @@
+//! Mouse motion along a path of waypoints.
+use glam::Vec2;
+
+/// How the mouse accelerates and decelerates along a path.
+#[derive(Clone, Copy, Debug, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum Easing {
+    /// Constant speed from start to end.
+    #[default]
+    Linear,
+    /// Cubic acceleration over the first half, deceleration over the second.
+    EaseInOut,
+    /// The minimum-jerk profile, which closely matches how people move a mouse.
+    MinimumJerk,
+}
+
+impl Easing {
+    /// Maps the fraction of time elapsed to the fraction of the path travelled.
+    pub fn apply(&self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Easing::Linear => t,
+            Easing::EaseInOut => {
+                if t < 0.5 {
+                    4.0 * t * t * t
+                } else {
+                    1.0 - (-2.0 * t + 2.0).powi(3) / 2.0
+                }
+            }
+            Easing::MinimumJerk => {
+                // symmetric about the middle, and evaluating the second half
+                // from the end keeps f32 rounding from going backwards near 1
+                let minimum_jerk = |t: f32| t * t * t * (10.0 - 15.0 * t + 6.0 * t * t);
+                if t < 0.5 {
+                    minimum_jerk(t)
+                } else {
+                    1.0 - minimum_jerk(1.0 - t)
+                }
+            }
+        }
+    }
+}
+
+/// The shape of the path between waypoints.
+#[derive(Clone, Copy, Debug, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum MotionCurve {
+    /// Straight lines between waypoints.
+    #[default]
+    Straight,
+    /// A smooth curve through every waypoint, made of cubic Bezier segments.
+    Bezier,
+}
+
+/// How fast the mouse moves along a path.
+///
+/// Speeds that would take a negative, infinite or undefined time move the
+/// mouse to the end of the path at once.
+#[derive(Clone, Copy, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum MotionSpeed {
+    /// Travel the whole path in this many seconds.
+    Seconds(f32),
+    /// Travel at this average speed.
+    PixelsPerSecond(f32),
+}
+
+impl Default for MotionSpeed {
+    fn default() -> Self {
+        MotionSpeed::PixelsPerSecond(1000.0)
+    }
+}
+
+/// How the mouse moves along a path of waypoints.
+#[derive(Clone, Copy, Debug, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct MouseMotion {
+    #[serde(default)]
+    pub easing: Easing,
+    #[serde(default)]
+    pub curve: MotionCurve,
+    #[serde(default)]
+    pub speed: MotionSpeed,
+    /// Maximum distance in pixels the mouse randomly strays from the path.
+    ///
+    /// Jitter tapers off towards the ends of the path, so the first and last
+    /// waypoints are still hit exactly.
+    #[serde(default)]
+    pub jitter: f32,
+    /// Seed for the jitter, so motions can be reproduced exactly.
+    #[serde(default)]
+    pub seed: u64,
+}
+
+impl MouseMotion {
+    /// Constant speed along straight lines, taking the given number of seconds.
+    pub fn linear(seconds: f32) -> Self {
+        MouseMotion {
+            speed: MotionSpeed::Seconds(seconds),
+            ..Default::default()
+        }
+    }
+
+    /// Plan the motion along the given waypoints.
+    pub fn trajectory(&self, waypoints: &[Vec2]) -> Trajectory {
+        let points = match self.curve {
+            MotionCurve::Straight => waypoints.to_vec(),
+            MotionCurve::Bezier => bezier_through(waypoints),
+        };
+        let mut cumulative = Vec::with_capacity(points.len());
+        let mut length = 0.0;
+        for (i, point) in points.iter().enumerate() {
+            if i > 0 {
+                length += points[i - 1].distance(*point);
+            }
+            cumulative.push(length);
+        }
+        let duration_in_seconds = match self.speed {
+            MotionSpeed::Seconds(seconds) => seconds,
+            MotionSpeed::PixelsPerSecond(speed) if speed > 0.0 => length / speed,
+            MotionSpeed::PixelsPerSecond(_) => 0.0,
+        };
+        // a motion that never ends would never stop sending positions
+        let duration_in_seconds = if duration_in_seconds.is_finite() {
+            duration_in_seconds.max(0.0)
+        } else {
+            0.0
+        };
+        Trajectory {
+            points,
+            cumulative,
+            duration_in_seconds,
+            easing: self.easing,
+            jitter: self.jitter,
+            seed: self.seed,
+        }
+    }
+}
+
+/// Number of straight segments each Bezier segment is flattened into.
+const BEZIER_SUBDIVISIONS: usize = 16;
+
+/// Flatten a Catmull-Rom spline through the waypoints, expressed as cubic Bezier
+/// segments, into a polyline.
+fn bezier_through(waypoints: &[Vec2]) -> Vec<Vec2> {
+    if waypoints.len() < 3 {
+        return waypoints.to_vec();
+    }
+    let mut points = vec![waypoints[0]];
+    for i in 0..waypoints.len() - 1 {
+        let p0 = waypoints[i.saturating_sub(1)];
+        let p1 = waypoints[i];
+        let p2 = waypoints[i + 1];
+        let p3 = waypoints[(i + 2).min(waypoints.len() - 1)];
+        let c1 = p1 + (p2 - p0) / 6.0;
+        let c2 = p2 - (p3 - p1) / 6.0;
+        for step in 1..=BEZIER_SUBDIVISIONS {
+            let t = step as f32 / BEZIER_SUBDIVISIONS as f32;
+            let u = 1.0 - t;
+            points.push(
+                p1 * (u * u * u)
+                    + c1 * (3.0 * u * u * t)
+                    + c2 * (3.0 * u * t * t)
+                    + p2 * (t * t * t),
+            );
+        }
+    }
+    points
+}
+
+/// A planned mouse motion, see [`MouseMotion::trajectory`].
+#[derive(Clone, Debug)]
+pub struct Trajectory {
+    points: Vec<Vec2>,
+    /// Distance along the path at each point.
+    cumulative: Vec<f32>,
+    duration_in_seconds: f32,
+    easing: Easing,
+    jitter: f32,
+    seed: u64,
+}
+
+impl Trajectory {
+    pub fn duration_in_seconds(&self) -> f32 {
+        self.duration_in_seconds
+    }
+
+    pub fn length(&self) -> f32 {
+        self.cumulative.last().copied().unwrap_or(0.0)
+    }
+
+    /// Returns the position of the mouse the given number of seconds into the
+    /// motion.
+    pub fn position_at(&self, elapsed_in_seconds: f32) -> Vec2 {
+        let Some(first) = self.points.first() else {
+            return Vec2::ZERO;
+        };
+        let t = if self.duration_in_seconds > 0.0 {
+            elapsed_in_seconds / self.duration_in_seconds
+        } else {
+            1.0
+        };
+        let s = self.easing.apply(t);
+        let distance = s * self.length();
+        let i = self
+            .cumulative
+            .partition_point(|d| *d < distance)
+            .clamp(1, self.points.len().max(2) - 1);
+        let Some(to) = self.points.get(i) else {
+            return *first;
+        };
+        let from = self.points[i - 1];
+        let segment = self.cumulative[i] - self.cumulative[i - 1];
+        let along = if segment > 0.0 {
+            (distance - self.cumulative[i - 1]) / segment
+        } else {
+            1.0
+        };
+        let position = from.lerp(*to, along);
+        if self.jitter > 0.0 {
+            let direction = (*to - from).normalize_or_zero().perp();
+            let taper = (s * std::f32::consts::PI).sin();
+            let millis = (elapsed_in_seconds * 1000.0) as u64;
+            position + direction * self.jitter * taper * random_unit(self.seed, millis)
+        } else {
+            position
+        }
+    }
+}
+
+/// A pseudo-random number in `-1.0..=1.0`, from the splitmix64 hash.
+///
+/// The same seed and step always give the same number, so jittered motion and
+/// typing can be reproduced exactly.
+#[doc(hidden)]
+pub fn random_unit(seed: u64, step: u64) -> f32 {
+    let mut z = seed ^ step.wrapping_mul(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^= z >> 31;
+    (z >> 40) as f32 / (1u64 << 23) as f32 - 1.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EASINGS: [Easing; 3] = [Easing::Linear, Easing::EaseInOut, Easing::MinimumJerk];
+
+    fn waypoints() -> Vec<Vec2> {
+        vec![
+            Vec2::new(0.0, 0.0),
+            Vec2::new(100.0, 50.0),
+            Vec2::new(200.0, 0.0),
+            Vec2::new(300.0, 80.0),
+        ]
+    }
+
+    #[test]
+    fn easing_starts_at_zero_and_ends_at_one() {
+        for easing in EASINGS {
+            assert_eq!(easing.apply(0.0), 0.0, "{easing:?}");
+            assert!((easing.apply(1.0) - 1.0).abs() < 1e-6, "{easing:?}");
+            assert_eq!(easing.apply(-1.0), 0.0, "{easing:?}");
+            assert!((easing.apply(2.0) - 1.0).abs() < 1e-6, "{easing:?}");
+        }
+    }
+
+    #[test]
+    fn easing_never_goes_backwards() {
+        for easing in EASINGS {
+            let mut previous = 0.0;
+            for i in 0..=1000 {
+                let s = easing.apply(i as f32 / 1000.0);
+                assert!(s >= previous, "{easing:?} went backwards at {i}");
+                previous = s;
+            }
+        }
+    }
+
+    #[test]
+    fn bezier_passes_through_every_waypoint() {
+        let waypoints = waypoints();
+        let points = bezier_through(&waypoints);
+        assert_eq!(
+            points.len(),
+            1 + (waypoints.len() - 1) * BEZIER_SUBDIVISIONS
+        );
+        for (i, waypoint) in waypoints.iter().enumerate() {
+            let point = points[i * BEZIER_SUBDIVISIONS];
+            assert!(point.distance(*waypoint) < 1e-3, "{point} != {waypoint}");
+        }
+        // two waypoints are a straight line
+        assert_eq!(bezier_through(&waypoints[..2]), waypoints[..2]);
+    }
+
+    #[test]
+    fn trajectory_hits_the_ends_exactly() {
+        let waypoints = waypoints();
+        for curve in [MotionCurve::Straight, MotionCurve::Bezier] {
+            for easing in EASINGS {
+                let motion = MouseMotion {
+                    easing,
+                    curve,
+                    jitter: 20.0,
+                    seed: 7,
+                    ..MouseMotion::linear(2.0)
+                };
+                let trajectory = motion.trajectory(&waypoints);
+                assert_eq!(trajectory.duration_in_seconds(), 2.0);
+                assert!(trajectory.position_at(0.0).distance(waypoints[0]) < 1e-3);
+                let end = trajectory.position_at(2.0);
+                assert!(
+                    end.distance(waypoints[3]) < 1e-3,
+                    "{curve:?} {easing:?} {end}"
+                );
+                assert!(trajectory.position_at(10.0).distance(waypoints[3]) < 1e-3);
+            }
+        }
+    }
+
+    #[test]
+    fn linear_straight_motion_moves_at_constant_speed() {
+        let trajectory = MouseMotion::linear(1.0).trajectory(&[Vec2::ZERO, Vec2::new(100.0, 0.0)]);
+        assert_eq!(trajectory.length(), 100.0);
+        assert!(trajectory.position_at(0.25).distance(Vec2::new(25.0, 0.0)) < 1e-3);
+        assert!(trajectory.position_at(0.5).distance(Vec2::new(50.0, 0.0)) < 1e-3);
+    }
+
+    #[test]
+    fn speed_sets_the_duration() {
+        let motion = MouseMotion {
+            speed: MotionSpeed::PixelsPerSecond(50.0),
+            ..Default::default()
+        };
+        let trajectory = motion.trajectory(&[Vec2::ZERO, Vec2::new(0.0, 100.0)]);
+        assert_eq!(trajectory.duration_in_seconds(), 2.0);
+    }
+
+    #[test]
+    fn invalid_speeds_take_no_time() {
+        let waypoints = [Vec2::ZERO, Vec2::new(0.0, 100.0)];
+        for speed in [
+            MotionSpeed::Seconds(f32::NAN),
+            MotionSpeed::Seconds(f32::INFINITY),
+            MotionSpeed::Seconds(-1.0),
+            MotionSpeed::PixelsPerSecond(f32::NAN),
+            MotionSpeed::PixelsPerSecond(f32::MIN_POSITIVE),
+            MotionSpeed::PixelsPerSecond(-1.0),
+        ] {
+            let motion = MouseMotion {
+                speed,
+                ..Default::default()
+            };
+            let trajectory = motion.trajectory(&waypoints);
+            assert_eq!(trajectory.duration_in_seconds(), 0.0, "{speed:?}");
+            assert_eq!(trajectory.position_at(0.0), waypoints[1], "{speed:?}");
+        }
+    }
+
+    #[test]
+    fn jitter_stays_within_bounds_and_is_reproducible() {
+        let waypoints = [Vec2::ZERO, Vec2::new(1000.0, 0.0)];
+        let motion = MouseMotion {
+            jitter: 5.0,
+            seed: 42,
+            ..MouseMotion::linear(1.0)
+        };
+        let trajectory = motion.trajectory(&waypoints);
+        let mut strayed = false;
+        for i in 0..=1000 {
+            let t = i as f32 / 1000.0;
+            let position = trajectory.position_at(t);
+            // the path is along the x axis, so any y is jitter
+            assert!(position.y.abs() <= 5.0, "strayed {} at {t}", position.y);
+            strayed |= position.y.abs() > 0.5;
+            assert_eq!(position, motion.trajectory(&waypoints).position_at(t));
+        }
+        assert!(strayed, "jitter had no effect");
+    }
+
+    #[test]
+    fn random_unit_is_in_range_and_deterministic() {
+        for step in 0..10_000 {
+            let unit = random_unit(3, step);
+            assert!((-1.0..=1.0).contains(&unit));
+            assert_eq!(unit, random_unit(3, step));
+        }
+        assert_ne!(random_unit(3, 0), random_unit(4, 0));
+    }
+
+    #[test]
+    fn empty_and_single_waypoint_paths_do_not_panic() {
+        let motion = MouseMotion::linear(1.0);
+        assert_eq!(motion.trajectory(&[]).position_at(0.5), Vec2::ZERO);
+        let point = Vec2::new(3.0, 4.0);
+        assert_eq!(motion.trajectory(&[point]).position_at(0.5), point);
+    }
+}
@@ -0,0 +1,14 @@
+//! Running processes.
+
+/// Information about a running process.
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct Process {
+    pub id: u32,
+    /// The name of the process, eg. `"Narrative Select"`.
+    pub name: String,
+    /// The path of the executable, if the driver may read it.
+    #[serde(default)]
+    pub path: Option<String>,
+    #[serde(default)]
+    pub parent_id: Option<u32>,
+}
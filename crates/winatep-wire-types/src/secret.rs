@@ -0,0 +1,288 @@
+//! Values that must never be logged.
+use std::fmt;
+
+/// A value, such as a password, that is redacted from `Debug` and `Display`
+/// output, and therefore from logs and traces.
+///
+/// On the wire a `Secret<T>` is serialized exactly like `T`.
+#[derive(Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(transparent)]
+pub struct Secret<T>(T);
+
+impl<T> Secret<T> {
+    pub fn new(value: T) -> Self {
+        Secret(value)
+    }
+
+    /// Returns the secret value. Take care not to log it.
+    pub fn expose_secret(&self) -> &T {
+        &self.0
+    }
+
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> fmt::Debug for Secret<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Secret([REDACTED])")
+    }
+}
+
+impl<T> fmt::Display for Secret<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("[REDACTED]")
+    }
+}
+
+impl From<String> for Secret<String> {
+    fn from(value: String) -> Self {
+        Secret(value)
+    }
+}
+
+impl From<&str> for Secret<String> {
+    fn from(value: &str) -> Self {
+        Secret(value.to_owned())
+    }
+}
+
+/// The text that replaces secrets in [`InputMessage::redacted`] and the like.
+///
+/// [`InputMessage::redacted`]: crate::InputMessage::redacted
+pub const REDACTED: &str = "[REDACTED]";
+
+/// Returns the text to write in place of a secret.
+pub type Redact<'a> = &'a dyn Fn(&str) -> String;
+
+fn redact_secret(secret: &Secret<String>, redact: Redact) -> Secret<String> {
+    Secret(redact(secret.expose_secret()))
+}
+
+impl crate::InputMessage {
+    /// Returns a copy of the message with every secret replaced, eg. to write
+    /// it to a file.
+    pub fn redacted(&self) -> Self {
+        self.redacted_with(&|_| REDACTED.to_owned())
+    }
+
+    /// Like [`Self::redacted`], but replaces each secret with the text
+    /// returned for it, eg. a digest.
+    pub fn redacted_with(&self, redact: Redact) -> Self {
+        use crate::InputMessage;
+
+        match self {
+            InputMessage::DoTypeText(text) => InputMessage::DoTypeText(redact_secret(text, redact)),
+            InputMessage::SetClipboardText(text) => {
+                InputMessage::SetClipboardText(redact_secret(text, redact))
+            }
+            InputMessage::SetClipboard(contents) => InputMessage::SetClipboard(
+                contents.iter().map(|c| c.redacted_with(redact)).collect(),
+            ),
+            InputMessage::LaunchApp {
+                path_or_bundle_id,
+                args,
+                env,
+            } => InputMessage::LaunchApp {
+                path_or_bundle_id: path_or_bundle_id.clone(),
+                args: args.clone(),
+                env: env
+                    .iter()
+                    .map(|(name, value)| (name.clone(), redact_secret(value, redact)))
+                    .collect(),
+            },
+            InputMessage::DoInput(token) => InputMessage::DoInput(token.redacted_with(redact)),
+            msg => msg.clone(),
+        }
+    }
+}
+
+impl crate::OutputMessage {
+    /// Returns a copy of the message with every secret replaced, eg. to write
+    /// it to a file.
+    pub fn redacted(&self) -> Self {
+        self.redacted_with(&|_| REDACTED.to_owned())
+    }
+
+    /// Like [`Self::redacted`], but replaces each secret with the text
+    /// returned for it, eg. a digest.
+    pub fn redacted_with(&self, redact: Redact) -> Self {
+        use crate::OutputMessage;
+
+        match self {
+            OutputMessage::GotClipboardText(text) => {
+                OutputMessage::GotClipboardText(redact_secret(text, redact))
+            }
+            OutputMessage::GotClipboard(content) => {
+                OutputMessage::GotClipboard(content.as_ref().map(|c| c.redacted_with(redact)))
+            }
+            OutputMessage::Event(event) => OutputMessage::Event(event.redacted_with(redact)),
+            msg => msg.clone(),
+        }
+    }
+}
+
+impl crate::TimestampedEvent {
+    /// Returns a copy of the event with any typed text replaced.
+    pub fn redacted(&self) -> Self {
+        self.redacted_with(&|_| REDACTED.to_owned())
+    }
+
+    pub fn redacted_with(&self, redact: Redact) -> Self {
+        use crate::DriverEvent;
+
+        let event = match &self.event {
+            DriverEvent::UserInput(token) => DriverEvent::UserInput(token.redacted_with(redact)),
+            DriverEvent::Recorded(token) => DriverEvent::Recorded(token.redacted_with(redact)),
+            event => event.clone(),
+        };
+        crate::TimestampedEvent {
+            timestamp_in_seconds: self.timestamp_in_seconds,
+            event,
+        }
+    }
+}
+
+impl crate::Token {
+    /// Returns a copy of the token with any text replaced.
+    pub fn redacted(&self) -> Self {
+        self.redacted_with(&|_| REDACTED.to_owned())
+    }
+
+    pub fn redacted_with(&self, redact: Redact) -> Self {
+        match self {
+            crate::Token::Text(text) => crate::Token::Text(redact(text)),
+            token => token.clone(),
+        }
+    }
+}
+
+impl crate::ClipboardContent {
+    /// Returns a copy of the content with any text replaced.
+    pub fn redacted(&self) -> Self {
+        self.redacted_with(&|_| REDACTED.to_owned())
+    }
+
+    pub fn redacted_with(&self, redact: Redact) -> Self {
+        use crate::ClipboardContent;
+
+        match self {
+            ClipboardContent::Text(text) => ClipboardContent::Text(redact_secret(text, redact)),
+            ClipboardContent::Html { html, alt_text } => ClipboardContent::Html {
+                html: redact_secret(html, redact),
+                alt_text: alt_text.as_ref().map(|text| redact_secret(text, redact)),
+            },
+            ClipboardContent::Rtf(text) => ClipboardContent::Rtf(redact_secret(text, redact)),
+            content => content.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        ClipboardContent, DriverEvent, InputMessage, OutputMessage, TimestampedEvent, Token,
+    };
+
+    const PASSWORD: &str = "hunter2";
+
+    #[test]
+    fn debug_and_display_are_redacted() {
+        let secret = Secret::from(PASSWORD);
+        assert!(!format!("{secret:?}").contains(PASSWORD));
+        assert!(!format!("{secret:#?}").contains(PASSWORD));
+        assert!(!format!("{secret}").contains(PASSWORD));
+        assert_eq!(secret.expose_secret(), PASSWORD);
+    }
+
+    #[test]
+    fn serializes_like_the_inner_value() {
+        let secret = Secret::from(PASSWORD);
+        let json = serde_json::to_string(&secret).unwrap();
+        assert_eq!(json, serde_json::to_string(PASSWORD).unwrap());
+        let back: Secret<String> = serde_json::from_str(&json).unwrap();
+        assert_eq!(back, secret);
+    }
+
+    #[test]
+    fn messages_are_redacted() {
+        for msg in [
+            InputMessage::DoTypeText(PASSWORD.into()),
+            InputMessage::SetClipboardText(PASSWORD.into()),
+            InputMessage::LaunchApp {
+                path_or_bundle_id: "com.apple.TextEdit".to_owned(),
+                args: vec![],
+                env: [("API_TOKEN".to_owned(), PASSWORD.into())].into(),
+            },
+        ] {
+            assert!(!format!("{msg:?}").contains(PASSWORD));
+            assert!(!format!("{msg:#?}").contains(PASSWORD));
+            assert!(serde_json::to_string(&msg).unwrap().contains(PASSWORD));
+            assert!(!serde_json::to_string(&msg.redacted())
+                .unwrap()
+                .contains(PASSWORD));
+        }
+    }
+
+    #[test]
+    fn typed_text_is_redacted() {
+        let event = |event| {
+            OutputMessage::Event(TimestampedEvent {
+                timestamp_in_seconds: 1.0,
+                event,
+            })
+        };
+        let sent = InputMessage::DoInput(Token::Text(PASSWORD.to_owned()));
+        assert!(!serde_json::to_string(&sent.redacted())
+            .unwrap()
+            .contains(PASSWORD));
+        for msg in [
+            event(DriverEvent::UserInput(Token::Text(PASSWORD.to_owned()))),
+            event(DriverEvent::Recorded(Token::Text(PASSWORD.to_owned()))),
+        ] {
+            assert!(!format!("{:?}", msg.redacted()).contains(PASSWORD));
+        }
+        let click =
+            InputMessage::DoInput(Token::Button(crate::Button::Left, crate::Direction::Click));
+        assert_eq!(format!("{:?}", click.redacted()), format!("{click:?}"));
+    }
+
+    fn clipboard_contents() -> Vec<ClipboardContent> {
+        vec![
+            ClipboardContent::Text(PASSWORD.into()),
+            ClipboardContent::Html {
+                html: format!("<b>{PASSWORD}</b>").into(),
+                alt_text: Some(PASSWORD.into()),
+            },
+            ClipboardContent::Rtf(format!("{{\\rtf1 {PASSWORD}}}").into()),
+        ]
+    }
+
+    #[test]
+    fn clipboard_text_is_not_logged() {
+        let sent = InputMessage::SetClipboard(clipboard_contents());
+        let received = [
+            OutputMessage::GotClipboardText(PASSWORD.into()),
+            OutputMessage::GotClipboard(clipboard_contents().pop()),
+        ];
+        assert!(!format!("{sent:?}").contains(PASSWORD));
+        assert!(!format!("{sent:#?}").contains(PASSWORD));
+        assert!(serde_json::to_string(&sent).unwrap().contains(PASSWORD));
+        assert!(!serde_json::to_string(&sent.redacted())
+            .unwrap()
+            .contains(PASSWORD));
+        for msg in received {
+            assert!(!format!("{msg:?}").contains(PASSWORD));
+            assert!(!format!("{msg:#?}").contains(PASSWORD));
+            assert!(serde_json::to_string(&msg).unwrap().contains(PASSWORD));
+            assert!(!serde_json::to_string(&msg.redacted())
+                .unwrap()
+                .contains(PASSWORD));
+        }
+        for content in clipboard_contents() {
+            assert!(!format!("{content:#?}").contains(PASSWORD));
+        }
+    }
+}
@@ -0,0 +1,410 @@
+//! Queries for text found within a screen.
+use std::ops::Range;
+
+use crate::BoundingRectangle;
+
+/// How the text of a [`TextQuery`] is matched.
+#[derive(Clone, Copy, Debug, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum TextMatchMode {
+    /// The text must appear as-is.
+    #[default]
+    Literal,
+    /// The text is a regular expression, in the syntax of the `regex` crate.
+    Regex,
+    /// The text may appear with up to `max_edit_distance` inserted, deleted or
+    /// substituted characters, eg. to tolerate OCR noise.
+    ///
+    /// The distance is clamped to one less than the length of the text, since
+    /// any text is within that many edits of every other.
+    Fuzzy { max_edit_distance: usize },
+}
+
+/// A query for text found within a screen.
+///
+/// Plain strings convert into literal, case-sensitive queries. On the wire
+/// those are sent as plain strings too, as drivers that predate queries expect.
+#[derive(Clone, Debug, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(from = "TextQueryRepr", into = "TextQueryRepr")]
+pub struct TextQuery {
+    pub text: String,
+    pub mode: TextMatchMode,
+    pub case_insensitive: bool,
+    /// Only match text that starts and ends on word boundaries.
+    pub whole_word: bool,
+}
+
+/// How a [`TextQuery`] is sent over the wire.
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(untagged)]
+enum TextQueryRepr {
+    Literal(String),
+    Query {
+        text: String,
+        #[serde(default)]
+        mode: TextMatchMode,
+        #[serde(default)]
+        case_insensitive: bool,
+        #[serde(default)]
+        whole_word: bool,
+    },
+}
+
+impl From<TextQueryRepr> for TextQuery {
+    fn from(repr: TextQueryRepr) -> Self {
+        match repr {
+            TextQueryRepr::Literal(text) => TextQuery::literal(text),
+            TextQueryRepr::Query {
+                text,
+                mode,
+                case_insensitive,
+                whole_word,
+            } => TextQuery {
+                text,
+                mode,
+                case_insensitive,
+                whole_word,
+            },
+        }
+    }
+}
+
+impl From<TextQuery> for TextQueryRepr {
+    fn from(query: TextQuery) -> Self {
+        let TextQuery {
+            text,
+            mode,
+            case_insensitive,
+            whole_word,
+        } = query;
+        if mode == TextMatchMode::Literal && !case_insensitive && !whole_word {
+            TextQueryRepr::Literal(text)
+        } else {
+            TextQueryRepr::Query {
+                text,
+                mode,
+                case_insensitive,
+                whole_word,
+            }
+        }
+    }
+}
+
+impl From<&str> for TextQuery {
+    fn from(text: &str) -> Self {
+        TextQuery::literal(text)
+    }
+}
+
+impl From<String> for TextQuery {
+    fn from(text: String) -> Self {
+        TextQuery::literal(text)
+    }
+}
+
+impl TextQuery {
+    pub fn literal(text: impl Into<String>) -> Self {
+        TextQuery {
+            text: text.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn regex(pattern: impl Into<String>) -> Self {
+        TextQuery {
+            text: pattern.into(),
+            mode: TextMatchMode::Regex,
+            ..Default::default()
+        }
+    }
+
+    pub fn fuzzy(text: impl Into<String>, max_edit_distance: usize) -> Self {
+        TextQuery {
+            text: text.into(),
+            mode: TextMatchMode::Fuzzy { max_edit_distance },
+            ..Default::default()
+        }
+    }
+
+    pub fn case_insensitive(mut self) -> Self {
+        self.case_insensitive = true;
+        self
+    }
+
+    pub fn whole_word(mut self) -> Self {
+        self.whole_word = true;
+        self
+    }
+
+    /// Prepare the query for matching against many strings.
+    ///
+    /// Errors if the query is an invalid regular expression.
+    pub fn matcher(&self) -> Result<TextMatcher, regex::Error> {
+        let inner = match self.mode {
+            TextMatchMode::Literal | TextMatchMode::Regex => {
+                let pattern = if self.mode == TextMatchMode::Literal {
+                    regex::escape(&self.text)
+                } else {
+                    self.text.clone()
+                };
+                let pattern = if self.whole_word {
+                    format!(r"\b(?:{pattern})\b")
+                } else {
+                    pattern
+                };
+                let regex = regex::RegexBuilder::new(&pattern)
+                    .case_insensitive(self.case_insensitive)
+                    .build()?;
+                Matcher::Regex(regex)
+            }
+            TextMatchMode::Fuzzy { max_edit_distance } => {
+                let pattern = fold_chars(&self.text, self.case_insensitive);
+                Matcher::Fuzzy {
+                    max_edit_distance: max_edit_distance.min(pattern.len().saturating_sub(1)),
+                    pattern,
+                    case_insensitive: self.case_insensitive,
+                    whole_word: self.whole_word,
+                }
+            }
+        };
+        Ok(TextMatcher { inner })
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Matcher {
+    Regex(regex::Regex),
+    Fuzzy {
+        pattern: Vec<char>,
+        max_edit_distance: usize,
+        case_insensitive: bool,
+        whole_word: bool,
+    },
+}
+
+/// A [`TextQuery`] prepared for matching.
+#[derive(Clone, Debug)]
+pub struct TextMatcher {
+    inner: Matcher,
+}
+
+impl TextMatcher {
+    /// Returns the byte ranges of all non-overlapping matches within the haystack.
+    pub fn find_all(&self, haystack: &str) -> Vec<Range<usize>> {
+        match &self.inner {
+            Matcher::Regex(regex) => regex.find_iter(haystack).map(|m| m.range()).collect(),
+            Matcher::Fuzzy {
+                pattern,
+                max_edit_distance,
+                case_insensitive,
+                whole_word,
+            } => find_fuzzy(
+                pattern,
+                *max_edit_distance,
+                *case_insensitive,
+                *whole_word,
+                haystack,
+            ),
+        }
+    }
+
+    /// Returns whether the query matches anywhere within the haystack.
+    pub fn is_match(&self, haystack: &str) -> bool {
+        !self.find_all(haystack).is_empty()
+    }
+}
+
+fn fold_chars(text: &str, case_insensitive: bool) -> Vec<char> {
+    text.chars()
+        .map(|c| {
+            if case_insensitive {
+                c.to_lowercase().next().unwrap_or(c)
+            } else {
+                c
+            }
+        })
+        .collect()
+}
+
+/// Approximate substring search, returning the best non-overlapping matches
+/// within the edit distance.
+///
+/// This is Sellers' algorithm: the edit distance table of the pattern against
+/// the haystack, where a match may start anywhere in the haystack for free.
+fn find_fuzzy(
+    pattern: &[char],
+    max_edit_distance: usize,
+    case_insensitive: bool,
+    whole_word: bool,
+    haystack: &str,
+) -> Vec<Range<usize>> {
+    if pattern.is_empty() {
+        return vec![];
+    }
+    let chars = fold_chars(haystack, case_insensitive);
+    // byte offset of each char, plus the end of the haystack
+    let offsets = haystack
+        .char_indices()
+        .map(|(i, _)| i)
+        .chain(std::iter::once(haystack.len()))
+        .collect::<Vec<_>>();
+    // whether a match may start or end at the char index without splitting a word
+    let is_boundary = |char_index: usize| -> bool {
+        let is_word = |c: char| c.is_alphanumeric() || c == '_';
+        let before = char_index
+            .checked_sub(1)
+            .and_then(|i| chars.get(i))
+            .is_some_and(|c| is_word(*c));
+        let after = chars.get(char_index).is_some_and(|c| is_word(*c));
+        !(before && after)
+    };
+
+    // `column[i]` is the (distance, start) of the best match of `pattern[..i]`
+    // ending at the current haystack position.
+    let mut column = (0..=pattern.len()).map(|i| (i, 0)).collect::<Vec<_>>();
+    // candidates of (distance, start, end), in char indices
+    let mut candidates: Vec<(usize, usize, usize)> = vec![];
+    for (j, c) in chars.iter().enumerate() {
+        let mut next = Vec::with_capacity(column.len());
+        next.push((0, j + 1));
+        for i in 1..=pattern.len() {
+            let substitution = (
+                column[i - 1].0 + usize::from(pattern[i - 1] != *c),
+                column[i - 1].1,
+            );
+            let deletion = (next[i - 1].0 + 1, next[i - 1].1);
+            let insertion = (column[i].0 + 1, column[i].1);
+            next.push(substitution.min(deletion).min(insertion));
+        }
+        column = next;
+        let (distance, start) = column[pattern.len()];
+        let end = j + 1;
+        if distance <= max_edit_distance
+            && start < end
+            && (!whole_word || (is_boundary(start) && is_boundary(end)))
+        {
+            candidates.push((distance, start, end));
+        }
+    }
+
+    // keep the closest of any overlapping candidates, preferring earlier and
+    // then longer ones
+    candidates.sort_by_key(|(distance, start, end)| (*distance, *start, std::cmp::Reverse(*end)));
+    let mut kept: Vec<(usize, usize)> = vec![];
+    for (_, start, end) in candidates {
+        if kept.iter().all(|(s, e)| end <= *s || start >= *e) {
+            kept.push((start, end));
+        }
+    }
+    kept.sort();
+    kept.into_iter()
+        .map(|(start, end)| offsets[start]..offsets[end])
+        .collect()
+}
+
+/// Text found within a screen.
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct TextMatch {
+    /// The text that matched the query, as it was recognized.
+    pub text: String,
+    /// Location of the text, in pixels relative to the screen.
+    pub location: BoundingRectangle,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn found<'a>(query: &TextQuery, haystack: &'a str) -> Vec<&'a str> {
+        let matcher = query.matcher().unwrap();
+        matcher
+            .find_all(haystack)
+            .into_iter()
+            .map(|range| &haystack[range])
+            .collect()
+    }
+
+    #[test]
+    fn literal_escapes_regex_syntax() {
+        let query = TextQuery::literal("a.b");
+        assert_eq!(found(&query, "a.b axb A.B"), ["a.b"]);
+    }
+
+    #[test]
+    fn regex_matches_patterns() {
+        let query = TextQuery::regex(r"v\d+\.\d+");
+        assert_eq!(found(&query, "from v1.2 to v10.0"), ["v1.2", "v10.0"]);
+        assert!(TextQuery::regex("(").matcher().is_err());
+    }
+
+    #[test]
+    fn case_insensitive_applies_to_every_mode() {
+        assert_eq!(
+            found(&TextQuery::literal("open").case_insensitive(), "Open OPEN"),
+            ["Open", "OPEN"]
+        );
+        assert_eq!(
+            found(&TextQuery::regex("op.n").case_insensitive(), "OPEN"),
+            ["OPEN"]
+        );
+        assert_eq!(
+            found(&TextQuery::fuzzy("open", 0).case_insensitive(), "OPEN"),
+            ["OPEN"]
+        );
+        assert!(found(&TextQuery::fuzzy("open", 0), "OPEN").is_empty());
+    }
+
+    #[test]
+    fn whole_word_skips_matches_within_words() {
+        let query = TextQuery::literal("cat").whole_word();
+        assert_eq!(found(&query, "concatenate cat, cats"), ["cat"]);
+        let query = TextQuery::fuzzy("cat", 1).whole_word();
+        assert_eq!(found(&query, "concatenate cut"), ["cut"]);
+    }
+
+    #[test]
+    fn fuzzy_respects_each_edit_distance() {
+        // one substitution, and two
+        let one = "Pr0jects";
+        let two = "Pr0jectz";
+        assert!(found(&TextQuery::fuzzy("Projects", 0), one).is_empty());
+        assert_eq!(found(&TextQuery::fuzzy("Projects", 1), one), [one]);
+        assert!(found(&TextQuery::fuzzy("Projects", 1), two).is_empty());
+        assert_eq!(found(&TextQuery::fuzzy("Projects", 2), two), [two]);
+        // insertions and deletions count as one edit each
+        assert_eq!(
+            found(&TextQuery::fuzzy("Projects", 1), "Proojects"),
+            ["Proojects"]
+        );
+        assert_eq!(
+            found(&TextQuery::fuzzy("Projects", 1), "Prjects"),
+            ["Prjects"]
+        );
+    }
+
+    #[test]
+    fn fuzzy_finds_non_overlapping_matches() {
+        let query = TextQuery::fuzzy("next", 1);
+        assert_eq!(found(&query, "Next | nexr | taxi"), ["Next", "nexr"]);
+    }
+
+    #[test]
+    fn fuzzy_distance_is_clamped_below_the_text_length() {
+        assert!(found(&TextQuery::fuzzy("ab", 2), "zz").is_empty());
+        assert!(found(&TextQuery::fuzzy("ab", 100), "zz").is_empty());
+        assert_eq!(found(&TextQuery::fuzzy("ab", 100), "zb"), ["zb"]);
+        assert!(found(&TextQuery::fuzzy("a", 5), "z").is_empty());
+        assert!(found(&TextQuery::fuzzy("", 5), "anything").is_empty());
+    }
+
+    #[test]
+    fn plain_queries_are_sent_as_strings() {
+        let json = serde_json::to_string(&TextQuery::literal("Next")).unwrap();
+        assert_eq!(json, r#""Next""#);
+        let query: TextQuery = serde_json::from_str(&json).unwrap();
+        assert_eq!(query, TextQuery::literal("Next"));
+
+        let fuzzy = TextQuery::fuzzy("Next", 1).case_insensitive();
+        let json = serde_json::to_string(&fuzzy).unwrap();
+        assert_eq!(serde_json::from_str::<TextQuery>(&json).unwrap(), fuzzy);
+    }
+}
@@ -0,0 +1,39 @@
+//! Application windows.
+use glam::Vec2;
+
+use crate::{BoundingRectangle, Screen};
+
+/// Information about an application window.
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct Window {
+    /// Identifies the window for as long as it is open.
+    pub id: u64,
+    pub title: String,
+    /// The ID of the process that owns the window.
+    pub process_id: u32,
+    /// The name of the process that owns the window, eg. `"Narrative Select"`.
+    pub process_name: String,
+    /// The bounds of the window in absolute logical coordinates.
+    pub bounds: BoundingRectangle,
+    /// The name of the screen that contains most of the window.
+    pub screen_name: String,
+    #[serde(default)]
+    pub focused: bool,
+    #[serde(default)]
+    pub minimized: bool,
+}
+
+impl Window {
+    /// Returns the bounds of the window in pixels relative to the screen, as
+    /// used for image and text search within that screen.
+    pub fn pixel_region(&self, screen: &Screen) -> BoundingRectangle {
+        BoundingRectangle {
+            min: screen.pixel_from_abs_point(self.bounds.min),
+            max: screen.pixel_from_abs_point(self.bounds.max),
+        }
+    }
+
+    pub fn size(&self) -> Vec2 {
+        self.bounds.max - self.bounds.min
+    }
+}